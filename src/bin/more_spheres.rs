@@ -1,38 +1,25 @@
-use std::f32;
-
-mod utils;
-use utils::Vector3;
-use utils::objects::{Sphere, Material};
-use utils::render_funcs::{cast_ray, save_ppm_image};
+use imgfun::Vector3;
+use imgfun::camera::Camera;
+use imgfun::color::srgb;
+use imgfun::objects::{Sphere, Material};
+use imgfun::scene::Scene;
+use imgfun::renderer::{Renderer, RenderSettings, print_progress};
 
 fn main() {
-    let ivory = Material::new(Vector3::new(0.4, 0.4, 0.3), None, None, None, None);
-    let red_rubber = Material::new(Vector3::new(0.3, 0.1, 0.1), None, None, None, None);
+    let ivory = Material::new(srgb(0.4, 0.4, 0.3), None, None, None, None);
+    let red_rubber = Material::new(srgb(0.3, 0.1, 0.1), None, None, None, None);
 
-    let spheres = vec![
-        Sphere::new(Vector3::new(-3., 0., -16.), 2., ivory.clone()),
-        Sphere::new(Vector3::new(-1.0, -1.5, -12.), 2., red_rubber.clone()),
-        Sphere::new(Vector3::new( 1.5, -0.5, -18.), 3., red_rubber),
-        Sphere::new(Vector3::new(7., 5., -18.), 4., ivory)
-    ];
+    let scene = Scene::builder()
+        .shape(Sphere::new(Vector3::new(-3., 0., -16.), 2., ivory.clone()))
+        .shape(Sphere::new(Vector3::new(-1.0, -1.5, -12.), 2., red_rubber.clone()))
+        .shape(Sphere::new(Vector3::new( 1.5, -0.5, -18.), 3., red_rubber))
+        .shape(Sphere::new(Vector3::new(7., 5., -18.), 4., ivory))
+        .build();
 
-    render(&spheres);
+    let settings = RenderSettings::from_args("more_spheres.ppm").expect("invalid arguments");
+    let camera = Camera::with_fov(60., settings.aspect_ratio());
+    let renderer = Renderer::new(settings).with_progress(print_progress);
+    let (image, sample_map) = renderer.render_with_sample_map(&scene, &camera);
+    renderer.save(&image).expect("failed to write image");
+    renderer.save_sample_map(&sample_map).expect("failed to write sample map");
 }
-
-fn render(spheres: &Vec<Sphere>) {
-    const WIDTH: usize = 800;
-    const HEIGHT: usize = 600;
-    const FOV: u32 = (f32::consts::PI/2.) as u32;
-    let mut pix_vec = vec![Vector3::new(0.,0.,0.); WIDTH*HEIGHT];
-    
-    for j in 0..HEIGHT {
-        for i in 0..WIDTH {
-            let x = (2. * (i as f32 + 0.5)/WIDTH as f32 - 1.) * (FOV as f32/2.).tan() * WIDTH as f32/HEIGHT as f32;
-            let y = -(2. * (j as f32 + 0.5)/HEIGHT as f32 - 1.) * (FOV as f32/2.).tan();
-            let dir = Vector3::new(x, y, -1.).normalize();
-            pix_vec[i+j*WIDTH] = cast_ray(&Vector3::new(0.,0.,0.), &dir, &spheres, None, None, false); 
-        }
-    }
-
-    save_ppm_image("more_spheres.ppm", WIDTH, HEIGHT, pix_vec);
-}
\ No newline at end of file
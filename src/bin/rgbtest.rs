@@ -1,21 +1,10 @@
-mod utils;
-use utils::Vector3;
-use utils::render_funcs::save_ppm_image;
+use imgfun::color::srgb;
+use imgfun::renderer::{Renderer, RenderSettings};
 
 fn main() {
-    render();
+    let settings = RenderSettings::from_args("test.ppm").expect("invalid arguments");
+    let height = settings.height;
+    let renderer = Renderer::new(settings);
+    // declared as sRGB so the written values ramp evenly
+    renderer.save(&renderer.render_pixels(|i, j| srgb(j as f32/height as f32, i as f32/height as f32, 0.))).expect("failed to write image");
 }
-
-fn render() {
-    const WIDTH: usize = 800;
-    const HEIGHT: usize = 600;
-    let mut pix_vec = vec![Vector3::new(0.,0.,0.); WIDTH*HEIGHT];
-    
-    for j in 0..HEIGHT {
-        for i in 0..WIDTH {
-            pix_vec[i+j*WIDTH] = Vector3::new(j as f32/HEIGHT as f32, i as f32/HEIGHT as f32, 0.); 
-        }
-    }
-
-    save_ppm_image("test.ppm", WIDTH, HEIGHT, pix_vec);
-}
\ No newline at end of file
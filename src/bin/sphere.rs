@@ -1,37 +1,25 @@
-use std::f32;
-
-mod utils;
-use utils::Vector3;
-use utils::objects::{Sphere, Material};
-use utils::render_funcs::save_ppm_image;
+use imgfun::Vector3;
+use imgfun::camera::Camera;
+use imgfun::color::srgb;
+use imgfun::objects::{Shape, Sphere, Material};
+use imgfun::renderer::{Renderer, RenderSettings};
 
 fn main() {
-    render(Sphere::new(Vector3::new(-3., 0., -16.), 2., Material::new(Vector3::new(-3., 0., -16.), None, None, None, None)));
-}
-
-fn render(sphere: Sphere) {
-    const WIDTH: usize = 800;
-    const HEIGHT: usize = 600;
-    const FOV: u32 = (f32::consts::PI/2.) as u32;
-    let mut pix_vec = vec![Vector3::new(0.,0.,0.); WIDTH*HEIGHT];
-    
-    for j in 0..HEIGHT {
-        for i in 0..WIDTH {
-            let x =  (2. * (i as f32 + 0.5)/WIDTH as f32  - 1.) * (FOV as f32/2.).tan() * WIDTH as f32/HEIGHT as f32;
-            let y = -(2. * (j as f32 + 0.5)/HEIGHT as f32 - 1.) * (FOV as f32/2.).tan();
-            let dir = Vector3::new(x, y, -1.).normalize();
-            pix_vec[i+j*WIDTH] = cast_ray(&Vector3::new(0.,0.,0.), &dir, &sphere); 
-        }
-    }
-
-    save_ppm_image("sphere.ppm", WIDTH, HEIGHT, pix_vec);
+    let sphere = Sphere::new(Vector3::new(-3., 0., -16.), 2., Material::new(Vector3::new(-3., 0., -16.), None, None, None, None));
+    let settings = RenderSettings::from_args("sphere.ppm").expect("invalid arguments");
+    let camera = Camera::with_fov(60., settings.aspect_ratio());
+    let (width, height) = (settings.width, settings.height);
+    let renderer = Renderer::new(settings);
+    renderer.save(&renderer.render_pixels(|i, j| {
+        let (orig, dir) = camera.primary_ray(i as f32 + 0.5, j as f32 + 0.5, width, height);
+        cast_ray(&orig, &dir, &sphere)
+    })).expect("failed to write image");
 }
 
 fn cast_ray(orig: &Vector3<f32>, dir: &Vector3<f32>, sphere: &Sphere) -> Vector3<f32> {
-    let mut sphere_dist: f32 = f32::MAX;
-    if !sphere.ray_intersect(orig, dir, &mut sphere_dist) {
-        return Vector3::new(0.2, 0.7, 0.8); // background color
+    if sphere.ray_intersect(orig, dir).is_none() {
+        return srgb(0.2, 0.7, 0.8); // background color
     }
 
-    Vector3::new(0.4, 0.4, 0.3)
+    srgb(0.4, 0.4, 0.3)
 }
\ No newline at end of file
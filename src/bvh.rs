@@ -0,0 +1,199 @@
+use std::f32;
+use crate::Vector3;
+
+/// Axis-aligned bounding box.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>
+}
+
+impl Aabb {
+    pub fn new(min: Vector3<f32>, max: Vector3<f32>) -> Aabb { Aabb { min, max } }
+
+    /// Box containing nothing, the starting point for growing one around a set of points or boxes.
+    pub fn empty() -> Aabb {
+        Aabb { min: Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY), max: Vector3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY) }
+    }
+
+    pub fn from_points<'a, I: IntoIterator<Item = &'a Vector3<f32>>>(points: I) -> Aabb {
+        points.into_iter().fold(Aabb::empty(), |b, p| b.grow(p))
+    }
+
+    pub fn grow(&self, p: &Vector3<f32>) -> Aabb {
+        Aabb { min: self.min.zip_map(p, f32::min), max: self.max.zip_map(p, f32::max) }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb { min: self.min.zip_map(&other.min, f32::min), max: self.max.zip_map(&other.max, f32::max) }
+    }
+
+    pub fn centroid(&self) -> Vector3<f32> { (self.min + self.max) / 2. }
+
+    pub fn surface_area(&self) -> f32 {
+        let d = (self.max - self.min).map(|e| e.max(0.));
+        2. * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    /// Distance at which the ray enters the box, if it does so before `max_distance`.
+    /// `inv_dir` is the componentwise reciprocal of the ray direction.
+    pub fn hit(&self, orig: &Vector3<f32>, inv_dir: &Vector3<f32>, max_distance: f32) -> Option<f32> {
+        let (mut near, mut far) = (0f32, max_distance);
+        for a in 0..3 {
+            let t1 = (self.min[a] - orig[a]) * inv_dir[a];
+            let t2 = (self.max[a] - orig[a]) * inv_dir[a];
+            near = near.max(t1.min(t2)); // f32::min/max skip the NaN of a ray lying in the slab's plane
+            far = far.min(t1.max(t2));
+        }
+        // a little slack so rounding never drops a hit the shape itself would report
+        if near <= far * (1. + 1e-5) { Some(near) } else { None }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    bounds: Aabb,
+    /// leaves: range in `Bvh::items`; interior nodes: `count` is 0, the left child follows directly and `start` is the right child
+    start: usize,
+    count: usize
+}
+
+/// Bounding volume hierarchy over a set of primitives identified by index, built with the surface area heuristic.
+/// It only knows the primitives' boxes; intersecting a primitive is up to the caller, so anything with
+/// bounds (shapes in a scene, triangles in a mesh) can be put in one.
+#[derive(Clone, Debug)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    items: Vec<usize>
+}
+
+const BINS: usize = 12;
+const MAX_LEAF: usize = 8;
+
+impl Bvh {
+    /// Builds the hierarchy over `(index, bounds)` pairs.
+    pub fn new(primitives: Vec<(usize, Aabb)>) -> Bvh {
+        let mut bvh = Bvh { nodes: Vec::with_capacity(2 * primitives.len()), items: Vec::with_capacity(primitives.len()) };
+        if !primitives.is_empty() {
+            let mut primitives = primitives;
+            bvh.build(&mut primitives);
+        }
+        bvh
+    }
+
+    pub fn bounds(&self) -> Option<Aabb> { self.nodes.first().map(|n| n.bounds) }
+
+    fn build(&mut self, primitives: &mut [(usize, Aabb)]) -> usize {
+        let bounds = primitives.iter().fold(Aabb::empty(), |b, p| b.union(&p.1));
+        let node = self.nodes.len();
+        self.nodes.push(Node { bounds, start: self.items.len(), count: primitives.len() });
+
+        let mid = match Bvh::split(primitives, &bounds) {
+            Some(mid) => mid,
+            None => {
+                self.items.extend(primitives.iter().map(|p| p.0));
+                return node;
+            }
+        };
+        let (left, right) = primitives.split_at_mut(mid);
+        self.build(left);
+        let right = self.build(right);
+        self.nodes[node].start = right;
+        self.nodes[node].count = 0;
+        node
+    }
+
+    /// Partitions the primitives along the cheapest binned SAH split and returns where the right half starts,
+    /// or None if they are better off left in a single leaf.
+    fn split(primitives: &mut [(usize, Aabb)], bounds: &Aabb) -> Option<usize> {
+        let n = primitives.len();
+        if n <= 1 { return None; }
+        let centroids = primitives.iter().fold(Aabb::empty(), |b, p| b.grow(&p.1.centroid()));
+        let extent = centroids.max - centroids.min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z { 0 } else if extent.y >= extent.z { 1 } else { 2 };
+        if extent[axis] <= 0. { // every centroid in the same spot, nothing to split on
+            return if n <= MAX_LEAF { None } else { Some(n / 2) };
+        }
+
+        let bin_of = |b: &Aabb| (((b.centroid()[axis] - centroids.min[axis]) / extent[axis] * BINS as f32) as usize).min(BINS - 1);
+        let mut bins = [(Aabb::empty(), 0usize); BINS];
+        for p in primitives.iter() {
+            let bin = &mut bins[bin_of(&p.1)];
+            bin.0 = bin.0.union(&p.1);
+            bin.1 += 1;
+        }
+
+        // cost of splitting after each bin: one traversal step plus each side's primitives weighted by its area
+        let mut best = (f32::INFINITY, 0);
+        for split in 1..BINS {
+            let side = |bins: &[(Aabb, usize)]| bins.iter().fold((Aabb::empty(), 0), |(b, c), bin| (b.union(&bin.0), c + bin.1));
+            let (left, nl) = side(&bins[..split]);
+            let (right, nr) = side(&bins[split..]);
+            if nl == 0 || nr == 0 { continue; }
+            let cost = 1. + (left.surface_area() * nl as f32 + right.surface_area() * nr as f32) / bounds.surface_area().max(f32::MIN_POSITIVE);
+            if cost < best.0 { best = (cost, split); }
+        }
+        if best.0 >= n as f32 && n <= MAX_LEAF { return None; }
+
+        let mut mid = 0;
+        for i in 0..n {
+            if bin_of(&primitives[i].1) < best.1 {
+                primitives.swap(i, mid);
+                mid += 1;
+            }
+        }
+        if mid == 0 || mid == n { mid = n / 2; }
+        Some(mid)
+    }
+
+    /// Closest hit along the ray nearer than `max_distance`. `intersect` is called with the index of each primitive
+    /// whose box the ray passes through and returns the hit's distance and whatever the caller wants back.
+    /// Ties go to the lowest index, so the result matches testing the primitives one by one in index order.
+    pub fn closest<T, F: FnMut(usize) -> Option<(f32, T)>>(&self, orig: &Vector3<f32>, dir: &Vector3<f32>, max_distance: f32, mut intersect: F) -> Option<T> {
+        let mut best: Option<(f32, usize, T)> = None;
+        if self.nodes.is_empty() { return None; }
+        let inv_dir = dir.map(|d| 1. / d);
+        let mut stack = vec![0];
+        while let Some(n) = stack.pop() {
+            let limit = best.as_ref().map_or(max_distance, |b| b.0);
+            let node = &self.nodes[n];
+            if node.bounds.hit(orig, &inv_dir, limit).is_none() { continue; }
+            if node.count > 0 {
+                for &i in &self.items[node.start..node.start + node.count] {
+                    if let Some((distance, hit)) = intersect(i) {
+                        let closer = match best {
+                            Some((d, b, _)) => distance < d || (distance == d && i < b),
+                            None => distance < max_distance
+                        };
+                        if closer { best = Some((distance, i, hit)); }
+                    }
+                }
+            } else {
+                // visit the nearer child first so the far one is more likely to be culled
+                let (left, right) = (n + 1, node.start);
+                let near = |c: usize| self.nodes[c].bounds.hit(orig, &inv_dir, limit).unwrap_or(f32::INFINITY);
+                if near(left) <= near(right) { stack.extend(&[right, left]); } else { stack.extend(&[left, right]); }
+            }
+        }
+        best.map(|b| b.2)
+    }
+
+    /// Whether any primitive is hit nearer than `max_distance`, stopping at the first one found.
+    pub fn any<F: FnMut(usize) -> Option<f32>>(&self, orig: &Vector3<f32>, dir: &Vector3<f32>, max_distance: f32, mut intersect: F) -> bool {
+        if self.nodes.is_empty() { return false; }
+        let inv_dir = dir.map(|d| 1. / d);
+        let mut stack = vec![0];
+        while let Some(n) = stack.pop() {
+            let node = &self.nodes[n];
+            if node.bounds.hit(orig, &inv_dir, max_distance).is_none() { continue; }
+            if node.count > 0 {
+                if self.items[node.start..node.start + node.count].iter().any(|&i| intersect(i).is_some_and(|d| d < max_distance)) {
+                    return true;
+                }
+            } else {
+                stack.extend(&[node.start, n + 1]);
+            }
+        }
+        false
+    }
+}
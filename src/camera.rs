@@ -0,0 +1,49 @@
+use crate::Vector3;
+
+/// A pinhole camera at `position` looking towards `look_at`.
+#[derive(Clone, Copy)]
+pub struct Camera {
+    pub position: Vector3<f32>,
+    pub look_at: Vector3<f32>,
+    pub up: Vector3<f32>,
+    /// vertical field of view, in degrees
+    pub fov: f32,
+    /// width / height of the image plane; should match the image being rendered or the shot will be stretched
+    pub aspect_ratio: f32
+}
+
+impl Camera {
+    pub fn new(position: Vector3<f32>, look_at: Vector3<f32>, up: Vector3<f32>, fov: f32, aspect_ratio: f32) -> Camera {
+        Camera { position, look_at, up, fov, aspect_ratio }
+    }
+
+    /// Camera with the given fov and aspect ratio at the origin, looking down -z with y up.
+    pub fn with_fov(fov: f32, aspect_ratio: f32) -> Camera {
+        Camera::new(Vector3::new(0., 0., 0.), Vector3::new(0., 0., -1.), Vector3::new(0., 1., 0.), fov, aspect_ratio)
+    }
+
+    /// Right, up and forward unit vectors of the camera.
+    pub fn basis(&self) -> (Vector3<f32>, Vector3<f32>, Vector3<f32>) {
+        let forward = (self.look_at - self.position).normalize();
+        let right = forward.cross(&self.up).normalize();
+        (right, right.cross(&forward), forward)
+    }
+
+    /// Origin and direction of the ray through `(s, t)` on the image plane, where (0, 0) is the top left corner and (1, 1) the bottom right.
+    pub fn ray(&self, s: f32, t: f32) -> (Vector3<f32>, Vector3<f32>) {
+        let (right, up, forward) = self.basis();
+        let half_height = (self.fov.to_radians() / 2.).tan();
+        let x = (2. * s - 1.) * half_height * self.aspect_ratio;
+        let y = -(2. * t - 1.) * half_height;
+        (self.position, (forward + right * x + up * y).normalize())
+    }
+
+    /// Primary ray through the point `(x, y)` of a `width` x `height` image, in pixels; pixel centers sit at `i + 0.5`.
+    pub fn primary_ray(&self, x: f32, y: f32, width: usize, height: usize) -> (Vector3<f32>, Vector3<f32>) {
+        self.ray(x / width as f32, y / height as f32)
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Camera { Camera::with_fov(60., 4. / 3.) }
+}
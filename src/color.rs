@@ -0,0 +1,46 @@
+use crate::Vector3;
+
+/// How the numbers of a color are to be read. Rendering always happens in linear light; sRGB is what
+/// 8-bit images and most color pickers use, so values in it are decoded on the way in and encoded on the way out.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColorSpace {
+    Linear,
+    Srgb
+}
+
+impl ColorSpace {
+    /// Converts a color given in this space to linear.
+    pub fn to_linear(self, c: Vector3<f32>) -> Vector3<f32> {
+        match self {
+            ColorSpace::Linear => c,
+            ColorSpace::Srgb => c.map(srgb_to_linear)
+        }
+    }
+
+    /// Converts a linear color into this space.
+    pub fn from_linear(self, c: Vector3<f32>) -> Vector3<f32> {
+        match self {
+            ColorSpace::Linear => c,
+            ColorSpace::Srgb => c.map(linear_to_srgb)
+        }
+    }
+}
+
+/// A color picked in sRGB (as in most paint programs and the original lesson), decoded to linear for rendering.
+pub fn srgb(r: f32, g: f32, b: f32) -> Vector3<f32> { ColorSpace::Srgb.to_linear(Vector3::new(r, g, b)) }
+
+/// A color that is already linear, spelled out for symmetry with `srgb`.
+pub fn linear(r: f32, g: f32, b: f32) -> Vector3<f32> { Vector3::new(r, g, b) }
+
+/// sRGB transfer function decode of a single channel.
+pub fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+}
+
+/// sRGB transfer function encode of a single channel.
+pub fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.003_130_8 { v * 12.92 } else { 1.055 * v.powf(1. / 2.4) - 0.055 }
+}
+
+/// Relative luminance of a linear color (Rec. 709 primaries).
+pub fn luminance(c: &Vector3<f32>) -> f32 { 0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z }
@@ -0,0 +1,96 @@
+use std::f32::consts::PI;
+use std::path::Path;
+use std::sync::Arc;
+use crate::{Vector2, Vector3};
+use crate::error::Result;
+use crate::formats;
+use crate::framebuffer::Framebuffer;
+use crate::texture::{ImageTexture, Texture, Wrap};
+
+/// How the environment's images cover the sphere of directions.
+#[derive(Clone)]
+pub enum Layout {
+    /// one image spanning all the way round horizontally and from straight down to straight up vertically,
+    /// with straight ahead (-z) in the middle
+    Equirectangular(ImageTexture),
+    /// six square faces in the order +x, -x, +y, -y, +z, -z. Each is the view along its axis from inside the cube
+    /// with +y up; the top face has -z at its bottom edge and the bottom face has -z at its top edge, as when
+    /// tilting your head up or down from looking ahead
+    Cubemap(Box<[ImageTexture; 6]>)
+}
+
+/// Light coming from infinitely far away in every direction, looked up by ray direction: what rays that miss
+/// everything see, in reflections and refractions too.
+#[derive(Clone)]
+pub struct EnvironmentMap {
+    pub layout: Layout,
+    /// degrees the environment is turned about the y axis, anticlockwise seen from above
+    pub rotation: f32,
+    /// multiplies every value, for balancing the environment against the lights
+    pub intensity: f32
+}
+
+impl EnvironmentMap {
+    pub fn equirectangular(image: Arc<Framebuffer>) -> EnvironmentMap {
+        EnvironmentMap { layout: Layout::Equirectangular(ImageTexture::new(image)), rotation: 0., intensity: 1. }
+    }
+
+    /// Faces in the order and orientation described at `Layout::Cubemap`.
+    pub fn cubemap(faces: [Arc<Framebuffer>; 6]) -> EnvironmentMap {
+        let faces = faces.map(|f| ImageTexture::new(f).with_wrap(Wrap::Clamp));
+        EnvironmentMap { layout: Layout::Cubemap(Box::new(faces)), rotation: 0., intensity: 1. }
+    }
+
+    /// Reads an equirectangular image with `formats::load`: Radiance HDR keeps the sky's full brightness,
+    /// 8-bit formats are decoded from sRGB.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<EnvironmentMap> {
+        Ok(EnvironmentMap::equirectangular(Arc::new(formats::load(path)?)))
+    }
+
+    /// Reads six cubemap faces, given in the order of `Layout::Cubemap`.
+    pub fn load_cubemap<P: AsRef<Path>>(paths: [P; 6]) -> Result<EnvironmentMap> {
+        let load = |p: &P| formats::load(p).map(Arc::new);
+        let [px, nx, py, ny, pz, nz] = &paths;
+        Ok(EnvironmentMap::cubemap([load(px)?, load(nx)?, load(py)?, load(ny)?, load(pz)?, load(nz)?]))
+    }
+
+    pub fn with_rotation(mut self, degrees: f32) -> EnvironmentMap {
+        self.rotation = degrees;
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> EnvironmentMap {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Radiance arriving from direction `dir` (pointing away from the viewer, as a ray's does).
+    pub fn color(&self, dir: &Vector3<f32>) -> Vector3<f32> {
+        // turning the environment one way is looking it up turned the other way
+        let (sin, cos) = (-self.rotation.to_radians()).sin_cos();
+        let d = Vector3::new(cos * dir.x + sin * dir.z, dir.y, -sin * dir.x + cos * dir.z).normalize();
+        let origin = Vector3::new(0., 0., 0.);
+        let color = match self.layout {
+            Layout::Equirectangular(ref texture) => {
+                // repeating round the horizon, but kept off the poles by half a pixel so filtering doesn't
+                // blend the top row into the bottom one
+                let half_pixel = 0.5 / texture.image.height().max(1) as f32;
+                let u = 0.5 + d.x.atan2(-d.z) / (2. * PI);
+                let v = (0.5 + d.y.clamp(-1., 1.).asin() / PI).clamp(half_pixel, 1. - half_pixel);
+                texture.value(&Vector2::new(u, v), &origin)
+            }
+            Layout::Cubemap(ref faces) => {
+                let a = d.abs();
+                // the face's index, then the directions of its image's right and up edges
+                let (face, right, up, major) = if a.x >= a.y && a.x >= a.z {
+                    if d.x > 0. { (0, Vector3::new(0., 0., 1.), Vector3::new(0., 1., 0.), a.x) } else { (1, Vector3::new(0., 0., -1.), Vector3::new(0., 1., 0.), a.x) }
+                } else if a.y >= a.z {
+                    if d.y > 0. { (2, Vector3::new(1., 0., 0.), Vector3::new(0., 0., 1.), a.y) } else { (3, Vector3::new(1., 0., 0.), Vector3::new(0., 0., -1.), a.y) }
+                } else if d.z > 0. { (4, Vector3::new(-1., 0., 0.), Vector3::new(0., 1., 0.), a.z) } else { (5, Vector3::new(1., 0., 0.), Vector3::new(0., 1., 0.), a.z) };
+                let uv = Vector2::new(right.dot(&d) / major + 1., up.dot(&d) / major + 1.) * 0.5;
+                faces[face].value(&uv, &origin)
+            }
+        };
+        color * self.intensity
+    }
+}
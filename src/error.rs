@@ -0,0 +1,35 @@
+use std::{error, fmt, io};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// the data doesn't follow the format it claims to be in
+    Format(String),
+    /// a valid file using a feature or variant this crate doesn't handle
+    Unsupported(String)
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Format(msg) => write!(f, "malformed file: {}", msg),
+            Error::Unsupported(msg) => write!(f, "unsupported: {}", msg)
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error { Error::Io(e) }
+}
@@ -0,0 +1,146 @@
+use std::str::FromStr;
+
+/// Weights a sample by its offset from the pixel center when averaging samples into a pixel.
+/// Filters are separable: the weight is the product of a one-dimensional profile along each axis,
+/// which is zero further than `radius` pixels from the center.
+pub trait Filter {
+    fn radius(&self) -> f32;
+    fn weight_1d(&self, d: f32) -> f32;
+
+    fn weight(&self, dx: f32, dy: f32) -> f32 { self.weight_1d(dx) * self.weight_1d(dy) }
+}
+
+/// Every sample inside the square counts the same.
+#[derive(Clone, Copy, Debug)]
+pub struct BoxFilter {
+    pub radius: f32
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> f32 { self.radius }
+    fn weight_1d(&self, d: f32) -> f32 { if d.abs() <= self.radius { 1. } else { 0. } }
+}
+
+/// Weight falls off linearly to zero at the radius.
+#[derive(Clone, Copy, Debug)]
+pub struct TentFilter {
+    pub radius: f32
+}
+
+impl Filter for TentFilter {
+    fn radius(&self) -> f32 { self.radius }
+    fn weight_1d(&self, d: f32) -> f32 { (self.radius - d.abs()).max(0.) }
+}
+
+/// Gaussian bell `exp(-alpha * d^2)`, shifted down so it reaches zero at the radius.
+#[derive(Clone, Copy, Debug)]
+pub struct GaussianFilter {
+    pub radius: f32,
+    pub alpha: f32
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> f32 { self.radius }
+    fn weight_1d(&self, d: f32) -> f32 {
+        ((-self.alpha * d * d).exp() - (-self.alpha * self.radius * self.radius).exp()).max(0.)
+    }
+}
+
+/// Mitchell-Netravali cubic; `b = c = 1/3` is the authors' recommended balance of blur and ringing.
+#[derive(Clone, Copy, Debug)]
+pub struct MitchellFilter {
+    pub radius: f32,
+    pub b: f32,
+    pub c: f32
+}
+
+impl Filter for MitchellFilter {
+    fn radius(&self) -> f32 { self.radius }
+    fn weight_1d(&self, d: f32) -> f32 {
+        let x = (2. * d / self.radius).abs(); // the cubic is defined over [-2, 2]
+        let (b, c) = (self.b, self.c);
+        let w = if x < 1. {
+            (12. - 9. * b - 6. * c) * x * x * x + (-18. + 12. * b + 6. * c) * x * x + (6. - 2. * b)
+        } else if x < 2. {
+            (-b - 6. * c) * x * x * x + (6. * b + 30. * c) * x * x + (-12. * b - 48. * c) * x + (8. * b + 24. * c)
+        } else {
+            0.
+        };
+        w / 6.
+    }
+}
+
+/// The built-in filters with their usual sizes, for picking one by name.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PixelFilter {
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell
+}
+
+impl Filter for PixelFilter {
+    fn radius(&self) -> f32 {
+        match self {
+            PixelFilter::Box => 0.5,
+            PixelFilter::Tent => 1.,
+            PixelFilter::Gaussian => 1.5,
+            PixelFilter::Mitchell => 2.
+        }
+    }
+
+    fn weight_1d(&self, d: f32) -> f32 {
+        let radius = self.radius();
+        match self {
+            PixelFilter::Box => BoxFilter { radius }.weight_1d(d),
+            PixelFilter::Tent => TentFilter { radius }.weight_1d(d),
+            PixelFilter::Gaussian => GaussianFilter { radius, alpha: 2. }.weight_1d(d),
+            PixelFilter::Mitchell => MitchellFilter { radius, b: 1. / 3., c: 1. / 3. }.weight_1d(d)
+        }
+    }
+}
+
+impl FromStr for PixelFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<PixelFilter, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "box" => Ok(PixelFilter::Box),
+            "tent" | "triangle" => Ok(PixelFilter::Tent),
+            "gaussian" => Ok(PixelFilter::Gaussian),
+            "mitchell" => Ok(PixelFilter::Mitchell),
+            _ => Err(format!("unknown filter {} (expected box, tent, gaussian or mitchell)", s))
+        }
+    }
+}
+
+/// Tabulated distribution of a filter's absolute weight along one axis, so sample offsets can be drawn
+/// where the filter matters instead of uniformly over its support (most of which weighs next to nothing).
+#[derive(Clone, Debug)]
+pub struct FilterSampler {
+    radius: f32,
+    pdf: Vec<f32>,
+    cdf: Vec<f32>
+}
+
+impl FilterSampler {
+    const BINS: usize = 64;
+
+    pub fn new<F: Filter + ?Sized>(filter: &F) -> FilterSampler {
+        let radius = filter.radius();
+        let bin = 2. * radius / FilterSampler::BINS as f32;
+        let mass: Vec<f32> = (0..FilterSampler::BINS).map(|b| filter.weight_1d(-radius + (b as f32 + 0.5) * bin).abs()).collect();
+        let total: f32 = mass.iter().sum();
+        let mut cdf = vec![0.];
+        for m in &mass { cdf.push(cdf[cdf.len() - 1] + m / total); }
+        FilterSampler { radius, pdf: mass.iter().map(|m| m / (total * bin)).collect(), cdf }
+    }
+
+    /// Offset from the pixel center for `u` in [0, 1), along with the density it was drawn with.
+    pub fn sample(&self, u: f32) -> (f32, f32) {
+        let b = (self.cdf.iter().position(|&c| c > u).unwrap_or(self.cdf.len()) - 1).min(FilterSampler::BINS - 1);
+        let t = ((u - self.cdf[b]) / (self.cdf[b + 1] - self.cdf[b])).clamp(0., 1.);
+        let bin = 2. * self.radius / FilterSampler::BINS as f32;
+        (-self.radius + (b as f32 + t) * bin, self.pdf[b])
+    }
+}
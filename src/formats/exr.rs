@@ -0,0 +1,116 @@
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::fs::File;
+use std::path::Path;
+use crate::error::{Error, Result};
+use crate::framebuffer::Framebuffer;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExrPixelType {
+    /// 16-bit floats, plenty for color and half the size
+    Half,
+    /// full 32-bit floats
+    Float
+}
+
+/// Saves the image as an uncompressed scanline OpenEXR file with R, G and B channels.
+pub fn save(path: &Path, image: &Framebuffer, pixel_type: ExrPixelType) -> Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write(&mut w, image, pixel_type)?;
+    w.flush()?;
+    Ok(())
+}
+
+pub fn write<W: Write>(w: &mut W, image: &Framebuffer, pixel_type: ExrPixelType) -> Result<()> {
+    let (width, height) = (image.width(), image.height());
+    if width == 0 || height == 0 { return Err(Error::Unsupported("empty OpenEXR images".to_string())); }
+    let (type_id, sample_size) = match pixel_type { ExrPixelType::Half => (1i32, 2), ExrPixelType::Float => (2i32, 4) };
+
+    let mut header = vec![];
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]); // magic number
+    header.extend_from_slice(&2i32.to_le_bytes()); // version 2, single part scanline file
+
+    // channels have to be listed in alphabetical order
+    let mut channels = vec![];
+    for name in b"BGR" {
+        channels.extend_from_slice(&[*name, 0]);
+        channels.extend_from_slice(&type_id.to_le_bytes());
+        channels.extend_from_slice(&[0, 0, 0, 0]); // pLinear and reserved
+        channels.extend_from_slice(&1i32.to_le_bytes()); // x sampling
+        channels.extend_from_slice(&1i32.to_le_bytes()); // y sampling
+    }
+    channels.push(0);
+    attribute(&mut header, "channels", "chlist", &channels);
+    attribute(&mut header, "compression", "compression", &[0]);
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1].iter().flat_map(|v| v.to_le_bytes().to_vec()).collect();
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]); // increasing y
+    attribute(&mut header, "pixelAspectRatio", "float", &1f32.to_le_bytes());
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0u8; 8]);
+    attribute(&mut header, "screenWindowWidth", "float", &1f32.to_le_bytes());
+    header.push(0);
+    w.write_all(&header)?;
+
+    // without compression every scanline is its own chunk: y, byte count, then each channel's samples in turn
+    let line_size = width * 3 * sample_size;
+    let chunk_size = 8 + line_size;
+    let first_chunk = header.len() + height * 8;
+    for y in 0..height {
+        w.write_all(&((first_chunk + y * chunk_size) as u64).to_le_bytes())?;
+    }
+    let mut line = Vec::with_capacity(line_size);
+    for (y, row) in image.rows().enumerate() {
+        line.clear();
+        for c in &[2, 1, 0] {
+            for p in row {
+                match pixel_type {
+                    ExrPixelType::Half => line.extend_from_slice(&f32_to_f16(p[*c]).to_le_bytes()),
+                    ExrPixelType::Float => line.extend_from_slice(&p[*c].to_le_bytes())
+                }
+            }
+        }
+        w.write_all(&(y as i32).to_le_bytes())?;
+        w.write_all(&(line_size as i32).to_le_bytes())?;
+        w.write_all(&line)?;
+    }
+    Ok(())
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// IEEE half precision bits for `v`, rounding to nearest even.
+fn f32_to_f16(v: f32) -> u16 {
+    let bits = v.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exp == 0xff { // infinity or NaN
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+    let exp = exp - 127 + 15;
+    if exp >= 0x1f { return sign | 0x7c00; } // too big, becomes infinity
+    if exp <= 0 { // subnormal or zero
+        if exp < -10 { return sign; }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exp) as u32;
+        let half = mantissa >> shift;
+        let rest = mantissa & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let round = rest > halfway || (rest == halfway && half & 1 == 1);
+        return sign | (half + round as u32) as u16;
+    }
+    let half = ((exp as u32) << 10) | (mantissa >> 13);
+    let rest = mantissa & 0x1fff;
+    let round = rest > 0x1000 || (rest == 0x1000 && half & 1 == 1);
+    // a carry out of the mantissa correctly bumps the exponent, up to infinity
+    sign | (half + round as u32) as u16
+}
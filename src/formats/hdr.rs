@@ -0,0 +1,37 @@
+use std::io::prelude::*;
+use std::io::{BufReader, BufWriter};
+use std::fs::File;
+use std::path::Path;
+use image::Rgb;
+use image::hdr::{HDRDecoder, HDREncoder};
+use crate::Vector3;
+use crate::error::{Error, Result};
+use crate::framebuffer::Framebuffer;
+
+/// Saves the image as a Radiance RGBE (.hdr) file, keeping values above 1.
+pub fn save(path: &Path, image: &Framebuffer) -> Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write(&mut w, image)?;
+    w.flush()?;
+    Ok(())
+}
+
+pub fn write<W: Write>(w: &mut W, image: &Framebuffer) -> Result<()> {
+    let data: Vec<Rgb<f32>> = image.pixels().iter().map(|p| Rgb { data: [p.x.max(0.), p.y.max(0.), p.z.max(0.)] }).collect();
+    HDREncoder::new(w).encode(&data, image.width(), image.height())?;
+    Ok(())
+}
+
+/// Reads a Radiance RGBE (.hdr) file, whose values are linear.
+pub fn load(path: &Path) -> Result<Framebuffer> {
+    read(BufReader::new(File::open(path)?))
+}
+
+pub fn read<R: BufRead>(r: R) -> Result<Framebuffer> {
+    let decoder = HDRDecoder::new(r).map_err(|e| Error::Format(e.to_string()))?;
+    let meta = decoder.metadata();
+    let pixels = decoder.read_image_hdr().map_err(|e| Error::Format(e.to_string()))?
+        .into_iter().map(|p| Vector3::new(p.data[0], p.data[1], p.data[2])).collect();
+    Framebuffer::from_pixels(meta.width as usize, meta.height as usize, pixels)
+        .ok_or_else(|| Error::Format("pixel count doesn't match the header".to_string()))
+}
@@ -0,0 +1,122 @@
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use image::{ColorType, bmp::BMPEncoder, jpeg::JPEGEncoder, png::PNGEncoder};
+use crate::Vector3;
+use crate::color::ColorSpace;
+use crate::error::{Error, Result};
+use crate::framebuffer::Framebuffer;
+
+pub mod exr;
+pub mod hdr;
+pub mod pfm;
+pub mod ppm;
+pub mod tga;
+
+use self::exr::ExrPixelType;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ImageFormat {
+    Ppm,
+    Png,
+    /// quality from 1 (smallest file) to 100 (best looking)
+    Jpeg(u8),
+    Bmp,
+    Tga,
+    /// Radiance RGBE
+    Hdr,
+    /// Portable FloatMap
+    Pfm,
+    Exr(ExrPixelType)
+}
+
+impl ImageFormat {
+    /// Format matching the path's extension, with JPEG at quality 90 and OpenEXR in half floats. PGM and PNM files
+    /// count as PPM, which reads them whatever they hold and writes color.
+    pub fn from_path(path: &Path) -> Result<ImageFormat> {
+        let ext = path.extension().and_then(OsStr::to_str).map(str::to_ascii_lowercase).unwrap_or_default();
+        match ext.as_str() {
+            "ppm" | "pgm" | "pnm" => Ok(ImageFormat::Ppm),
+            "png" => Ok(ImageFormat::Png),
+            "jpg" | "jpeg" => Ok(ImageFormat::Jpeg(90)),
+            "bmp" => Ok(ImageFormat::Bmp),
+            "tga" => Ok(ImageFormat::Tga),
+            "hdr" => Ok(ImageFormat::Hdr),
+            "pfm" => Ok(ImageFormat::Pfm),
+            "exr" => Ok(ImageFormat::Exr(ExrPixelType::Half)),
+            _ => Err(Error::Unsupported(format!("no image format for extension {:?}", ext)))
+        }
+    }
+
+    /// Whether the format stores float radiance as-is rather than 8-bit display values.
+    pub fn is_hdr(&self) -> bool {
+        matches!(self, ImageFormat::Hdr | ImageFormat::Pfm | ImageFormat::Exr(_))
+    }
+}
+
+/// Saves the image in the format picked from the file extension.
+pub fn save<P: AsRef<Path>>(path: P, image: &Framebuffer) -> Result<()> {
+    let path = path.as_ref();
+    save_as(path, image, ImageFormat::from_path(path)?)
+}
+
+pub fn save_as<P: AsRef<Path>>(path: P, image: &Framebuffer, format: ImageFormat) -> Result<()> {
+    let path = path.as_ref();
+    match format {
+        ImageFormat::Ppm => ppm::save(path, image, ppm::PpmFormat::Binary),
+        ImageFormat::Tga => tga::save(path, image),
+        ImageFormat::Hdr => hdr::save(path, image),
+        ImageFormat::Pfm => pfm::save(path, image),
+        ImageFormat::Exr(pixel_type) => exr::save(path, image, pixel_type),
+        ImageFormat::Png | ImageFormat::Jpeg(_) | ImageFormat::Bmp => encode(path, image, format)
+    }
+}
+
+/// Reads an image, picking the format by the file extension: PPM or PGM, PNG, JPEG, BMP or TGA, decoded from sRGB,
+/// or Radiance HDR as it is. PFM and OpenEXR can't be read yet.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Framebuffer> {
+    let path = path.as_ref();
+    let codec = match ImageFormat::from_path(path)? {
+        ImageFormat::Ppm => return ppm::load(path),
+        ImageFormat::Hdr => return hdr::load(path),
+        ImageFormat::Png => image::ImageFormat::PNG,
+        ImageFormat::Jpeg(_) => image::ImageFormat::JPEG,
+        ImageFormat::Bmp => image::ImageFormat::BMP,
+        ImageFormat::Tga => image::ImageFormat::TGA,
+        format => return Err(Error::Unsupported(format!("reading {:?} images", format)))
+    };
+    decode(image::load_from_memory_with_format(&fs::read(path)?, codec))
+}
+
+/// Decodes an image held in memory, such as one embedded in a model file, telling PPM, PNG, JPEG, BMP and
+/// Radiance HDR apart by their first bytes.
+pub fn read(bytes: &[u8]) -> Result<Framebuffer> {
+    if bytes.len() > 1 && bytes[0] == b'P' && bytes[1].is_ascii_digit() { return ppm::read(bytes, ColorSpace::Srgb); }
+    if bytes.starts_with(b"#?") { return hdr::read(bytes); }
+    decode(image::load_from_memory(bytes))
+}
+
+/// Converts what the image crate decoded into linear floats.
+fn decode(decoded: image::ImageResult<image::DynamicImage>) -> Result<Framebuffer> {
+    let image = decoded.map_err(|e| Error::Format(e.to_string()))?.to_rgb();
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let pixels = image.pixels()
+        .map(|p| ColorSpace::Srgb.to_linear(Vector3::new(p[0] as f32, p[1] as f32, p[2] as f32) / 255.))
+        .collect();
+    Ok(Framebuffer::from_pixels(width, height, pixels).expect("the decoder gives one pixel per position"))
+}
+
+/// Formats written through the image crate's encoders.
+fn encode(path: &Path, image: &Framebuffer, format: ImageFormat) -> Result<()> {
+    let (width, height) = (image.width() as u32, image.height() as u32);
+    let mut w = BufWriter::new(File::create(path)?);
+    match format {
+        ImageFormat::Png => PNGEncoder::new(&mut w).encode(&image.to_rgb8(), width, height, ColorType::RGB(8))?,
+        ImageFormat::Jpeg(quality) => JPEGEncoder::new_with_quality(&mut w, quality.clamp(1, 100)).encode(&image.to_rgb8(), width, height, ColorType::RGB(8))?,
+        ImageFormat::Bmp => BMPEncoder::new(&mut w).encode(&image.to_rgb8(), width, height, ColorType::RGB(8))?,
+        _ => unreachable!("{:?} has its own writer", format)
+    }
+    w.flush()?;
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::fs::File;
+use std::path::Path;
+use crate::error::Result;
+use crate::framebuffer::Framebuffer;
+
+/// Saves the image as a color Portable FloatMap (.pfm), 32-bit floats per channel.
+pub fn save(path: &Path, image: &Framebuffer) -> Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write(&mut w, image)?;
+    w.flush()?;
+    Ok(())
+}
+
+pub fn write<W: Write>(w: &mut W, image: &Framebuffer) -> Result<()> {
+    // a negative scale marks the data as little endian
+    write!(w, "PF\n{} {}\n-1.0\n", image.width(), image.height())?;
+    // PFM stores the bottom row first
+    for row in image.rows().rev() {
+        for p in row {
+            for c in p.iter() { w.write_all(&c.to_le_bytes())?; }
+        }
+    }
+    Ok(())
+}
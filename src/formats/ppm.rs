@@ -0,0 +1,179 @@
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::fs::File;
+use std::path::Path;
+use crate::Vector3;
+use crate::color::ColorSpace;
+use crate::error::{Error, Result};
+use crate::framebuffer::{to_u8, Framebuffer};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PpmFormat {
+    /// P3, human readable decimal values
+    Ascii,
+    /// P6, raw bytes; much smaller and faster to write
+    Binary
+}
+
+pub fn save(path: &Path, image: &Framebuffer, format: PpmFormat) -> Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write(&mut w, image, format)?;
+    w.flush()?;
+    Ok(())
+}
+
+/// Plain PPM readers needn't accept lines longer than this.
+const ASCII_LINE_LENGTH: usize = 70;
+
+/// Streams the image out as 8-bit sRGB PPM, one row at a time.
+pub fn write<W: Write>(w: &mut W, image: &Framebuffer, format: PpmFormat) -> Result<()> {
+    let magic = if format == PpmFormat::Ascii { "P3" } else { "P6" };
+    write!(w, "{}\n{} {}\n255\n", magic, image.width(), image.height())?;
+    let mut bytes = Vec::with_capacity(image.width() * 3);
+    let mut line = String::with_capacity(ASCII_LINE_LENGTH + 1);
+    for row in image.rows() {
+        bytes.clear();
+        for p in row { bytes.extend_from_slice(&to_u8(ColorSpace::Srgb.from_linear(*p))); }
+        match format {
+            PpmFormat::Binary => w.write_all(&bytes)?,
+            PpmFormat::Ascii => for v in &bytes {
+                let value = v.to_string();
+                if !line.is_empty() && line.len() + 1 + value.len() > ASCII_LINE_LENGTH {
+                    writeln!(w, "{}", line)?;
+                    line.clear();
+                }
+                if !line.is_empty() { line.push(' '); }
+                line.push_str(&value);
+            }
+        }
+    }
+    if !line.is_empty() { writeln!(w, "{}", line)?; }
+    Ok(())
+}
+
+/// Reads a PPM (P3/P6) or PGM (P2/P5) file, scaling values by the file's maxval into [0, 1] and decoding them from sRGB.
+pub fn load(path: &Path) -> Result<Framebuffer> { load_in(path, ColorSpace::Srgb) }
+
+/// Reads a PPM or PGM file whose values are in the given color space.
+pub fn load_in(path: &Path, space: ColorSpace) -> Result<Framebuffer> {
+    let mut bytes = vec![];
+    File::open(path)?.read_to_end(&mut bytes)?;
+    read(&bytes, space)
+}
+
+pub fn read(bytes: &[u8], space: ColorSpace) -> Result<Framebuffer> {
+    let mut header = Header { bytes, pos: 0 };
+    let magic = header.token()?;
+    let (channels, binary) = match magic.as_str() {
+        "P2" => (1, false),
+        "P3" => (3, false),
+        "P5" => (1, true),
+        "P6" => (3, true),
+        "P1" | "P4" => return Err(Error::Unsupported("PBM bitmaps".to_string())),
+        _ => return Err(Error::Format(format!("unknown magic number {:?}", magic)))
+    };
+    let width = header.number()?;
+    let height = header.number()?;
+    let maxval = header.number()?;
+    if maxval == 0 || maxval > 65535 { return Err(Error::Format(format!("maxval {} out of range", maxval))); }
+
+    let count = width.checked_mul(height).and_then(|n| n.checked_mul(channels))
+        .ok_or_else(|| Error::Format(format!("image size {}x{} is too large", width, height)))?;
+    let values: Vec<usize> = if binary {
+        // exactly one whitespace byte separates the header from the raster
+        let raster = &bytes[(header.pos + 1).min(bytes.len())..];
+        let sample_bytes = if maxval < 256 { 1 } else { 2 };
+        if raster.len() < count * sample_bytes { return Err(Error::Format("raster is shorter than the image size".to_string())); }
+        if sample_bytes == 1 {
+            raster[..count].iter().map(|&b| b as usize).collect()
+        } else {
+            raster[..count * 2].chunks(2).map(|b| (b[0] as usize) << 8 | b[1] as usize).collect()
+        }
+    } else {
+        (0..count).map(|_| header.number()).collect::<Result<_>>()?
+    };
+
+    let scale = 1. / maxval as f32;
+    let pixels = values.chunks(channels).map(|v| {
+        let c = if channels == 1 {
+            let g = v[0] as f32 * scale;
+            Vector3::new(g, g, g)
+        } else {
+            Vector3::new(v[0] as f32 * scale, v[1] as f32 * scale, v[2] as f32 * scale)
+        };
+        space.to_linear(c)
+    }).collect();
+    Ok(Framebuffer::from_pixels(width, height, pixels).expect("raster size matches header"))
+}
+
+/// Whitespace separated header fields, skipping `#` comments.
+struct Header<'a> {
+    bytes: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Header<'a> {
+    fn token(&mut self) -> Result<String> {
+        loop {
+            match self.bytes.get(self.pos) {
+                Some(b'#') => while self.bytes.get(self.pos).is_some_and(|&b| b != b'\n') { self.pos += 1; },
+                Some(b) if b.is_ascii_whitespace() => self.pos += 1,
+                Some(_) => break,
+                None => return Err(Error::Format("unexpected end of file".to_string()))
+            }
+        }
+        let start = self.pos;
+        while self.bytes.get(self.pos).is_some_and(|b| !b.is_ascii_whitespace()) { self.pos += 1; }
+        Ok(String::from_utf8_lossy(&self.bytes[start..self.pos]).into_owned())
+    }
+
+    fn number(&mut self) -> Result<usize> {
+        let t = self.token()?;
+        t.parse().map_err(|_| Error::Format(format!("expected a number, found {:?}", t)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 5x3 gradient whose values survive the round trip through 8-bit sRGB unchanged.
+    fn gradient() -> Framebuffer {
+        let pixels = (0..15).map(|i| {
+            ColorSpace::Srgb.to_linear(Vector3::new((i * 17) as f32, (255 - i * 13) as f32, ((i * 40) % 256) as f32) / 255.)
+        }).collect();
+        Framebuffer::from_pixels(5, 3, pixels).unwrap()
+    }
+
+    fn written(image: &Framebuffer, format: PpmFormat) -> Vec<u8> {
+        let mut bytes = vec![];
+        write(&mut bytes, image, format).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn round_trips() {
+        let image = gradient();
+        for &format in &[PpmFormat::Ascii, PpmFormat::Binary] {
+            let read = read(&written(&image, format), ColorSpace::Srgb).unwrap();
+            assert_eq!((read.width(), read.height()), (5, 3));
+            assert_eq!(read.to_rgb8(), image.to_rgb8(), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn ascii_lines_are_short() {
+        let image = Framebuffer::from_pixels(40, 2, vec![Vector3::new(1., 1., 1.); 80]).unwrap();
+        let text = String::from_utf8(written(&image, PpmFormat::Ascii)).unwrap();
+        assert!(text.lines().all(|l| l.len() <= ASCII_LINE_LENGTH));
+        assert_eq!(text.lines().skip(3).flat_map(str::split_whitespace).count(), 240);
+    }
+
+    #[test]
+    fn short_raster_is_an_error() {
+        let mut binary = written(&gradient(), PpmFormat::Binary);
+        binary.truncate(binary.len() - 1);
+        assert!(read(&binary, ColorSpace::Srgb).is_err());
+        assert!(read(b"P2\n2 2\n255\n0 64 128\n", ColorSpace::Srgb).is_err());
+    }
+}
@@ -0,0 +1,32 @@
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::fs::File;
+use std::path::Path;
+use crate::error::{Error, Result};
+use crate::framebuffer::Framebuffer;
+
+pub fn save(path: &Path, image: &Framebuffer) -> Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write(&mut w, image)?;
+    w.flush()?;
+    Ok(())
+}
+
+/// Writes an uncompressed 24-bit true-color TGA, stored top row first.
+pub fn write<W: Write>(w: &mut W, image: &Framebuffer) -> Result<()> {
+    if image.width() > u16::MAX as usize || image.height() > u16::MAX as usize {
+        return Err(Error::Unsupported(format!("TGA images larger than {0}x{0}", u16::MAX)));
+    }
+    let (width, height) = (image.width() as u16, image.height() as u16);
+    let mut header = [0u8; 18];
+    header[2] = 2; // uncompressed true-color
+    header[12..14].copy_from_slice(&width.to_le_bytes());
+    header[14..16].copy_from_slice(&height.to_le_bytes());
+    header[16] = 24; // bits per pixel
+    header[17] = 0x20; // origin in the top left corner
+    w.write_all(&header)?;
+    let mut bgr = image.to_rgb8();
+    for p in bgr.chunks_mut(3) { p.swap(0, 2); }
+    w.write_all(&bgr)?;
+    Ok(())
+}
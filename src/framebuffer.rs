@@ -0,0 +1,97 @@
+use std::slice::{ChunksExact, ChunksExactMut};
+use crate::Vector3;
+use crate::color::ColorSpace;
+use crate::tonemap::ToneMapper;
+
+/// A `width` x `height` image of linear float RGB pixels, stored row by row from the top left.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vector3<f32>>
+}
+
+impl Framebuffer {
+    /// Black image of the given size.
+    pub fn new(width: usize, height: usize) -> Framebuffer {
+        Framebuffer { width, height, pixels: vec![Vector3::new(0., 0., 0.); width * height] }
+    }
+
+    /// Wraps existing pixels, or returns None if there aren't exactly `width * height` of them.
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Vector3<f32>>) -> Option<Framebuffer> {
+        if pixels.len() != width * height { return None; }
+        Some(Framebuffer { width, height, pixels })
+    }
+
+    pub fn width(&self) -> usize { self.width }
+
+    pub fn height(&self) -> usize { self.height }
+
+    pub fn pixels(&self) -> &[Vector3<f32>] { &self.pixels }
+
+    pub fn pixels_mut(&mut self) -> &mut [Vector3<f32>] { &mut self.pixels }
+
+    pub fn into_pixels(self) -> Vec<Vector3<f32>> { self.pixels }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<Vector3<f32>> {
+        if x >= self.width || y >= self.height { return None; }
+        Some(self.pixels[x + y * self.width])
+    }
+
+    /// Writes the pixel at (x, y), returning false (and leaving the image untouched) if it lies outside the image.
+    pub fn set(&mut self, x: usize, y: usize, color: Vector3<f32>) -> bool {
+        if x >= self.width || y >= self.height { return false; }
+        self.pixels[x + y * self.width] = color;
+        true
+    }
+
+    pub fn rows(&self) -> ChunksExact<'_, Vector3<f32>> { self.pixels.chunks_exact(self.width.max(1)) }
+
+    pub fn rows_mut(&mut self) -> ChunksExactMut<'_, Vector3<f32>> { self.pixels.chunks_exact_mut(self.width.max(1)) }
+
+    /// New image with `f` applied to every pixel.
+    pub fn map<F: Fn(Vector3<f32>) -> Vector3<f32>>(&self, f: F) -> Framebuffer {
+        Framebuffer { width: self.width, height: self.height, pixels: self.pixels.iter().map(|&c| f(c)).collect() }
+    }
+
+    /// New image with every pixel passed through the tone mapper, ready for 8-bit output.
+    pub fn tone_mapped(&self, mapper: &dyn ToneMapper) -> Framebuffer { self.map(|c| mapper.map(c)) }
+
+    /// A single channel (0 = red, 1 = green, 2 = blue) of every pixel.
+    pub fn channel(&self, c: usize) -> Vec<f32> { self.pixels.iter().map(|p| p[c]).collect() }
+
+    /// Interleaved RGB floats, three per pixel.
+    pub fn to_rgb_f32(&self) -> Vec<f32> {
+        let mut values = Vec::with_capacity(self.pixels.len() * 3);
+        for p in self.pixels.iter() { values.extend_from_slice(&[p.x, p.y, p.z]); }
+        values
+    }
+
+    /// Interleaved 8-bit sRGB, with every channel clamped to [0, 1] first.
+    pub fn to_rgb8(&self) -> Vec<u8> { self.to_rgb8_in(ColorSpace::Srgb) }
+
+    /// Interleaved 8-bit sRGB with an opaque alpha channel.
+    pub fn to_rgba8(&self) -> Vec<u8> { self.to_rgba8_in(ColorSpace::Srgb) }
+
+    /// Interleaved 8-bit RGB encoded in the given color space.
+    pub fn to_rgb8_in(&self, space: ColorSpace) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.pixels.len() * 3);
+        for p in self.pixels.iter() { bytes.extend_from_slice(&to_u8(space.from_linear(*p))); }
+        bytes
+    }
+
+    pub fn to_rgba8_in(&self, space: ColorSpace) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.pixels.len() * 4);
+        for p in self.pixels.iter() {
+            bytes.extend_from_slice(&to_u8(space.from_linear(*p)));
+            bytes.push(255);
+        }
+        bytes
+    }
+}
+
+/// Quantizes an already encoded color to 8 bits per channel, clamping it to [0, 1] first.
+pub(crate) fn to_u8(c: Vector3<f32>) -> [u8; 3] {
+    let q = |v: f32| (255. * 0f32.max(1f32.min(v)) + 0.5) as u8;
+    [q(c.x), q(c.y), q(c.z)]
+}
@@ -0,0 +1,24 @@
+// the single-letter vector names (I, N, L) follow the notation of the original lesson
+#![allow(non_snake_case)]
+
+pub use nalgebra::{Vector2, Vector3, Vector4};
+
+pub mod bvh;
+pub mod camera;
+pub mod color;
+pub mod environment;
+pub mod error;
+pub mod filter;
+pub mod formats;
+pub mod framebuffer;
+pub mod loaders;
+pub mod mesh;
+pub mod noise;
+pub mod objects;
+pub mod render_funcs;
+pub mod sampling;
+pub mod renderer;
+pub mod scene;
+pub mod texture;
+pub mod tiles;
+pub mod tonemap;
@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use ::gltf::{Gltf, Node};
+use ::gltf::buffer::Source;
+use ::gltf::camera::Projection;
+use ::gltf::image::Source as ImageSource;
+use ::gltf::khr_lights_punctual::Kind;
+use ::gltf::material::AlphaMode;
+use ::gltf::mesh::Mode;
+use ::gltf::texture::{MagFilter, WrappingMode};
+use nalgebra::{Matrix3, Matrix4};
+use crate::{Vector2, Vector3, Vector4};
+use crate::bvh::Aabb;
+use crate::camera::Camera;
+use crate::error::{Error, Result};
+use crate::formats;
+use crate::framebuffer::Framebuffer;
+use crate::objects::{Light, Material, MaterialQuality};
+use crate::scene::{Scene, SceneBuilder};
+use crate::texture::{ImageTexture, Interpolation, Wrap};
+use super::{Group, Model};
+
+/// How far away directional lights are put, since `Light` is always a point: well past the scene's render distance.
+const SUN_DISTANCE: f32 = 1e4;
+
+/// Everything read from a glTF file, already placed in world space.
+pub struct GltfScene {
+    /// one model per mesh primitive per node using it
+    pub models: Vec<Model>,
+    /// scaled from the file's candela and lux as described at `load`
+    pub lights: Vec<Light>,
+    /// how much the file's photometric intensities were multiplied by to give the lights' `intensity`
+    pub light_scale: f32,
+    /// perspective cameras in node order; their aspect ratio is the file's, or 4/3 when it doesn't say
+    pub cameras: Vec<Camera>,
+    /// parts of the file that couldn't be brought over faithfully, such as spot lights' cones
+    pub warnings: Vec<String>
+}
+
+/// A light as the file gives it, before its intensity is scaled to the renderer's.
+struct PhotometricLight {
+    position: Vector3<f32>,
+    color: Vector3<f32>,
+    /// lux for directional lights, which are the same at any distance
+    illuminance: Option<f32>,
+    /// candela for point and spot lights
+    candela: f32
+}
+
+impl GltfScene {
+    /// Scene builder holding all the models and lights, for adding to or changing settings on before building.
+    pub fn builder(&self) -> SceneBuilder {
+        let mut builder = Scene::builder().lights(self.lights.clone());
+        for m in &self.models { builder = builder.shapes(m.shapes()); }
+        builder
+    }
+
+    pub fn scene(&self) -> Scene { self.builder().build() }
+
+    /// The file's first camera, made to fit an image of the given aspect ratio.
+    pub fn camera(&self, aspect_ratio: f32) -> Option<Camera> {
+        self.cameras.first().map(|c| Camera { aspect_ratio, ..*c })
+    }
+}
+
+/// Reads a `.gltf` (with its buffers in separate files or embedded as data URIs) or `.glb` file and walks its
+/// default scene. Meshes come in with their node transforms applied; materials are mapped from metallic-roughness
+/// (plus the transmission and IOR extensions) onto the crate's `Material`; perspective cameras become `Camera`s and
+/// KHR_lights_punctual lights become `Light`s, with directional lights placed far away.
+/// Base color textures are loaded too; other textures, orthographic cameras, skins and animations are ignored.
+///
+/// `Light` has no falloff, so the file's candela (point and spot lights) and lux (directional lights) can't be used
+/// as they are. Each light's intensity is instead the illuminance it gives at the center of the meshes' bounds, and
+/// all of them are scaled together so they add up to 1 there: the lights keep their balance, whatever units the
+/// exporter meant. Spot lights lose their cone and shine all round, with a warning in `GltfScene::warnings`.
+pub fn load(path: &Path) -> Result<GltfScene> {
+    let gltf = Gltf::from_slice(&fs::read(path)?).map_err(|e| Error::Format(e.to_string()))?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let buffers = gltf.buffers().map(|b| {
+        let data = match b.source() {
+            Source::Bin => gltf.blob.clone().ok_or_else(|| Error::Format("no binary chunk".to_string()))?,
+            Source::Uri(uri) => read_uri(uri, dir)?
+        };
+        if data.len() < b.length() { return Err(Error::Format(format!("buffer {} is shorter than declared", b.index()))); }
+        Ok(data)
+    }).collect::<Result<Vec<Vec<u8>>>>()?;
+
+    let mut imported = GltfScene { models: vec![], lights: vec![], light_scale: 1., cameras: vec![], warnings: vec![] };
+    let mut lights = vec![];
+    let mut images = Images { dir, buffers: &buffers, loaded: HashMap::new() };
+    let scene = gltf.default_scene().or_else(|| gltf.scenes().next())
+        .ok_or_else(|| Error::Format("no scenes".to_string()))?;
+    for node in scene.nodes() {
+        visit(&node, &Matrix4::identity(), &buffers, &mut images, &mut imported, &mut lights)?;
+    }
+
+    let bounds = Aabb::from_points(imported.models.iter().flat_map(|m| m.positions.iter()));
+    let center = if bounds.min.x <= bounds.max.x { (bounds.min + bounds.max) * 0.5 } else { Vector3::new(0., 0., 0.) };
+    let illuminance = |l: &PhotometricLight| l.illuminance
+        .unwrap_or_else(|| l.candela / (l.position - center).norm_squared().max(1e-6));
+    let total: f32 = lights.iter().map(illuminance).sum();
+    if total > 0. { imported.light_scale = 1. / total; }
+    imported.lights = lights.iter()
+        .map(|l| Light::new(&l.position, &(illuminance(l) * imported.light_scale)).with_color(l.color))
+        .collect();
+    Ok(imported)
+}
+
+/// Decoded texture images by index, so materials sharing one only decode it once.
+struct Images<'a> {
+    dir: &'a Path,
+    buffers: &'a [Vec<u8>],
+    loaded: HashMap<usize, Arc<Framebuffer>>
+}
+
+impl<'a> Images<'a> {
+    fn get(&mut self, image: ::gltf::Image) -> Result<Arc<Framebuffer>> {
+        if let Some(loaded) = self.loaded.get(&image.index()) { return Ok(loaded.clone()); }
+        let decoded = Arc::new(match image.source() {
+            ImageSource::View { view, .. } => {
+                let buffer = &self.buffers[view.buffer().index()];
+                let bytes = buffer.get(view.offset()..view.offset() + view.length())
+                    .ok_or_else(|| Error::Format(format!("image {} lies past the end of its buffer", image.index())))?;
+                formats::read(bytes)?
+            }
+            ImageSource::Uri { uri, .. } => formats::read(&read_uri(uri, self.dir)?)?
+        });
+        self.loaded.insert(image.index(), decoded.clone());
+        Ok(decoded)
+    }
+}
+
+fn visit(node: &Node, parent: &Matrix4<f32>, buffers: &[Vec<u8>], images: &mut Images, imported: &mut GltfScene,
+         lights: &mut Vec<PhotometricLight>) -> Result<()> {
+    let local = node.transform().matrix();
+    let transform = parent * Matrix4::from_fn(|r, c| local[c][r]); // glTF matrices are column-major
+    let point = |p: [f32; 3]| { let v = transform * Vector4::new(p[0], p[1], p[2], 1.); Vector3::new(v.x, v.y, v.z) };
+    let direction = |d: [f32; 3]| { let v = transform * Vector4::new(d[0], d[1], d[2], 0.); Vector3::new(v.x, v.y, v.z).normalize() };
+
+    if let Some(camera) = node.camera() {
+        if let Projection::Perspective(p) = camera.projection() {
+            let position = point([0., 0., 0.]);
+            imported.cameras.push(Camera::new(position, position + direction([0., 0., -1.]), direction([0., 1., 0.]),
+                p.yfov().to_degrees(), p.aspect_ratio().unwrap_or(4. / 3.)));
+        }
+    }
+
+    if let Some(light) = node.light() {
+        let [r, g, b] = light.color();
+        let (position, illuminance) = match light.kind() {
+            Kind::Directional => (-direction([0., 0., -1.]) * SUN_DISTANCE, Some(light.intensity())),
+            Kind::Point => (point([0., 0., 0.]), None),
+            Kind::Spot { .. } => {
+                imported.warnings.push(format!("spot light {} shines all round: lights have no cone",
+                    light.name().map_or_else(|| light.index().to_string(), |n| format!("\"{}\"", n))));
+                (point([0., 0., 0.]), None)
+            }
+        };
+        lights.push(PhotometricLight { position, color: Vector3::new(r, g, b), illuminance, candela: light.intensity() });
+    }
+
+    if let Some(mesh) = node.mesh() {
+        let linear = Matrix3::from_fn(|r, c| transform[(r, c)]);
+        let normal_matrix = linear.try_inverse().map_or(linear, |m| m.transpose());
+        let mirrored = linear.determinant() < 0.; // flips the winding, so the triangles must be turned back around
+
+        for primitive in mesh.primitives() {
+            let reader = primitive.reader(|b| buffers.get(b.index()).map(|d| &d[..]));
+            let positions: Vec<Vector3<f32>> = match reader.read_positions() {
+                Some(p) => p.map(point).collect(),
+                None => continue
+            };
+            let count = positions.len();
+            let indices: Vec<usize> = match reader.read_indices() {
+                Some(i) => i.into_u32().map(|i| i as usize).collect(),
+                None => (0..count).collect()
+            };
+            if indices.iter().any(|&i| i >= count) {
+                return Err(Error::Format(format!("mesh {} indexes past its vertices", mesh.index())));
+            }
+            let mut triangles: Vec<[usize; 3]> = match primitive.mode() {
+                Mode::Triangles => indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect(),
+                Mode::TriangleStrip => (2..indices.len()).map(|i| if i % 2 == 0 { [indices[i - 2], indices[i - 1], indices[i]] } else { [indices[i - 1], indices[i - 2], indices[i]] }).collect(),
+                Mode::TriangleFan => (2..indices.len()).map(|i| [indices[0], indices[i - 1], indices[i]]).collect(),
+                _ => continue // points and lines have no surface to hit
+            };
+            if mirrored { for t in &mut triangles { t.swap(1, 2); } }
+
+            let normals = reader.read_normals().map(|n| n.map(|n| (normal_matrix * Vector3::new(n[0], n[1], n[2])).normalize()).collect::<Vec<_>>());
+            // glTF counts v down from the top of the image
+            let uvs = reader.read_tex_coords(0).map(|t| t.into_f32().map(|t| Vector2::new(t[0], 1. - t[1])).collect::<Vec<_>>());
+            let colors = reader.read_colors(0).map(|c| c.into_rgb_f32().map(|c| Vector3::new(c[0], c[1], c[2])).collect::<Vec<_>>());
+            let complete = |n: usize| n == count;
+
+            imported.models.push(Model {
+                positions: Arc::new(positions),
+                normals: normals.filter(|n| complete(n.len())).map(Arc::new),
+                uvs: uvs.filter(|t| complete(t.len())).map(Arc::new),
+                colors: colors.filter(|c| complete(c.len())).map(Arc::new),
+                groups: vec![Group { name: mesh.name().unwrap_or_default().to_string(), material: material(&primitive.material(), images)?, triangles }]
+            });
+        }
+    }
+
+    for child in node.children() {
+        visit(&child, &transform, buffers, images, imported, lights)?;
+    }
+    Ok(())
+}
+
+/// Maps a metallic-roughness material onto the Phong-style `Material`: metals lose their diffuse part and reflect
+/// tinted by their base color, roughness widens the highlight (by the usual GGX to Blinn-Phong exponent match) and
+/// dims reflections, and transmission or blended alpha lets light through by refraction. A base color texture using
+/// the first set of texture coordinates becomes the diffuse texture, tinted by the base color.
+fn material(m: &::gltf::Material, images: &mut Images) -> Result<Material> {
+    let pbr = m.pbr_metallic_roughness();
+    let [r, g, b, alpha] = pbr.base_color_factor();
+    let (metallic, roughness) = (pbr.metallic_factor(), pbr.roughness_factor().max(0.01));
+
+    let f0 = 0.04 * (1. - metallic) + metallic * (r + g + b) / 3.;
+    let reflect = f0 * (1. - roughness);
+    let transmission = m.transmission().map_or(0., |t| t.transmission_factor());
+    let transmission = if m.alpha_mode() == AlphaMode::Blend { transmission.max(1. - alpha) } else { transmission };
+
+    let mut qualities = vec![MaterialQuality::Smooth];
+    if reflect > 0.01 || transmission > 0. { qualities.push(MaterialQuality::Reflective); }
+    if transmission > 0. { qualities.push(MaterialQuality::Refractive); }
+    let exponent = (2. / roughness.powi(4) - 2.).clamp(1., 10000.);
+    let albedo = Vector4::new((1. - metallic) * (1. - transmission), 1. - roughness, reflect, transmission);
+    let mut material = Material::new(Vector3::new(r, g, b), Some(albedo), Some(m.ior().unwrap_or(1.5)), Some(exponent), Some(qualities));
+
+    if let Some(info) = pbr.base_color_texture().filter(|i| i.tex_coord() == 0) {
+        let texture = info.texture();
+        let sampler = texture.sampler();
+        let wrap = match sampler.wrap_s() { // one mode for both axes, so the horizontal one stands for both
+            WrappingMode::ClampToEdge => Wrap::Clamp,
+            WrappingMode::MirroredRepeat => Wrap::Mirror,
+            WrappingMode::Repeat => Wrap::Repeat
+        };
+        let interpolation = if sampler.mag_filter() == Some(MagFilter::Nearest) { Interpolation::Nearest } else { Interpolation::Bilinear };
+        let image = ImageTexture::new(images.get(texture.source())?).with_wrap(wrap).with_interpolation(interpolation).with_tint(Vector3::new(r, g, b));
+        material = material.with_diffuse_texture(Arc::new(image));
+    }
+    Ok(material)
+}
+
+/// Bytes a buffer or image URI points to: embedded in a base64 data URI, or a file next to the glTF file.
+fn read_uri(uri: &str, dir: &Path) -> Result<Vec<u8>> {
+    if uri.starts_with("data:") {
+        let encoded = uri.split(";base64,").nth(1).ok_or_else(|| Error::Unsupported("data URI that isn't base64".to_string()))?;
+        return base64_decode(encoded);
+    }
+    Ok(fs::read(dir.join(percent_decode(uri)))?)
+}
+
+/// Undoes the `%XX` escapes of a relative URI, such as the `%20` exporters write for spaces in file names.
+/// A `%` not followed by two hex digits is kept as it is.
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let hex = |i: usize| bytes.get(i).and_then(|&b| (b as char).to_digit(16));
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], hex(i + 1), hex(i + 2)) {
+            (b'%', Some(high), Some(low)) => {
+                out.push((high * 16 + low) as u8);
+                i += 3;
+            }
+            (b, _, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Decodes standard base64, as used by glTF data URIs.
+fn base64_decode(s: &str) -> Result<Vec<u8>> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None
+    };
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    let (mut bits, mut n) = (0u32, 0);
+    for c in s.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+        bits = bits << 6 | value(c).ok_or_else(|| Error::Format(format!("bad base64 character {:?}", c as char)))? as u32;
+        n += 6;
+        if n >= 8 {
+            n -= 8;
+            out.push((bits >> n) as u8);
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_escaped_file_names() {
+        assert_eq!(percent_decode("My%20Texture.png"), "My Texture.png");
+        assert_eq!(percent_decode("textures/caf%C3%A9.jpg"), "textures/café.jpg");
+        assert_eq!(percent_decode("100%.bin"), "100%.bin");
+        assert_eq!(percent_decode("a%2"), "a%2");
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::sync::Arc;
+use crate::{Vector2, Vector3};
+use crate::color::srgb;
+use crate::error::{Error, Result};
+use crate::mesh::TriangleMesh;
+use crate::objects::{Material, Shape};
+
+pub mod gltf;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+/// Triangle geometry read from a model file. Every group shares the same vertex buffers, indexed alike:
+/// vertex `i` is at `positions[i]`, with `normals[i]`, `uvs[i]` and `colors[i]` when the file has them.
+pub struct Model {
+    pub positions: Arc<Vec<Vector3<f32>>>,
+    pub normals: Option<Arc<Vec<Vector3<f32>>>>,
+    pub uvs: Option<Arc<Vec<Vector2<f32>>>>,
+    /// linear vertex colors, which multiply the materials' diffuse colors
+    pub colors: Option<Arc<Vec<Vector3<f32>>>>,
+    pub groups: Vec<Group>
+}
+
+/// The triangles of a model that belong to one named part and use one material.
+pub struct Group {
+    pub name: String,
+    pub material: Material,
+    pub triangles: Vec<[usize; 3]>
+}
+
+impl Model {
+    /// One mesh per group, all sharing the model's vertex buffers.
+    pub fn meshes(&self) -> Vec<TriangleMesh> {
+        self.groups.iter().map(|g| {
+            let mut mesh = TriangleMesh::new(self.positions.clone(), g.triangles.clone(), g.material.clone());
+            if let Some(ref normals) = self.normals { mesh = mesh.with_normals(normals.clone()); }
+            if let Some(ref uvs) = self.uvs { mesh = mesh.with_uvs(uvs.clone()); }
+            if let Some(ref colors) = self.colors { mesh = mesh.with_colors(colors.clone()); }
+            mesh
+        }).collect()
+    }
+
+    /// The meshes boxed up for `SceneBuilder::shapes`.
+    pub fn shapes(&self) -> Vec<Box<dyn Shape>> {
+        self.meshes().into_iter().map(|m| Box::new(m) as Box<dyn Shape>).collect()
+    }
+
+    /// Scales the model about the origin by `scale`, then moves it by `offset`, to place it in a scene.
+    /// A negative scale turns the model inside out, so its normals are flipped and its triangles wound the other way
+    /// round to keep both pointing out.
+    pub fn transformed(mut self, scale: f32, offset: Vector3<f32>) -> Model {
+        self.positions = Arc::new(self.positions.iter().map(|p| p * scale + offset).collect());
+        if scale < 0. {
+            self.normals = self.normals.map(|n| Arc::new(n.iter().map(|n| -n).collect()));
+            for g in &mut self.groups {
+                for t in &mut g.triangles { t.swap(1, 2); }
+            }
+        }
+        self
+    }
+
+    /// Gives a model without normals smooth ones: each corner gets the area-weighted average of the faces around its
+    /// vertex that meet its own face at no more than `max_angle` degrees, so curved surfaces shade smoothly while
+    /// sharper edges stay crisp. Vertices on such an edge are split, one copy per side. Models that already have
+    /// normals are left as they are.
+    pub fn with_generated_normals(mut self, max_angle: f32) -> Model {
+        if self.normals.is_some() { return self; }
+        let min_cos = max_angle.to_radians().cos();
+        let positions = self.positions.clone();
+        let face_normal = |t: &[usize; 3]| (positions[t[1]] - positions[t[0]]).cross(&(positions[t[2]] - positions[t[0]]));
+
+        // every face around each vertex, across all the groups since they share the vertices
+        let faces: Vec<Vector3<f32>> = self.groups.iter().flat_map(|g| g.triangles.iter().map(face_normal)).collect();
+        let mut around = vec![vec![]; positions.len()];
+        let mut f = 0;
+        for g in &self.groups {
+            for t in &g.triangles {
+                for &i in t { around[i].push(f); }
+                f += 1;
+            }
+        }
+
+        let mut vertices: Vec<(usize, Vector3<f32>)> = vec![];
+        let mut index = HashMap::new();
+        let mut copies = vec![None; positions.len()];
+        let mut degenerate = vec![];
+        let mut f = 0;
+        for g in &mut self.groups {
+            for t in &mut g.triangles {
+                let own = match faces[f].try_normalize(0.) {
+                    Some(n) => n,
+                    None => { // can't be hit, so it just reuses whichever copy of its vertices there is
+                        degenerate.push(t);
+                        f += 1;
+                        continue;
+                    }
+                };
+                for i in t.iter_mut() {
+                    let normal = around[*i].iter()
+                        .filter(|&&other| faces[other].try_normalize(0.).is_some_and(|n| own.dot(&n) >= min_cos))
+                        .fold(Vector3::new(0., 0., 0.), |sum, &other| sum + faces[other])
+                        .normalize();
+                    let key = (*i, [normal.x.to_bits(), normal.y.to_bits(), normal.z.to_bits()]);
+                    let vertex = *index.entry(key).or_insert_with(|| {
+                        vertices.push((*i, normal));
+                        vertices.len() - 1
+                    });
+                    copies[*i].get_or_insert(vertex);
+                    *i = vertex;
+                }
+                f += 1;
+            }
+        }
+        for t in degenerate {
+            for i in t.iter_mut() {
+                *i = *copies[*i].get_or_insert_with(|| {
+                    vertices.push((*i, Vector3::new(0., 0., 0.)));
+                    vertices.len() - 1
+                });
+            }
+        }
+
+        self.positions = Arc::new(vertices.iter().map(|v| positions[v.0]).collect());
+        self.normals = Some(Arc::new(vertices.iter().map(|v| v.1).collect()));
+        self.uvs = self.uvs.map(|uvs| Arc::new(vertices.iter().map(|v| uvs[v.0]).collect()));
+        self.colors = self.colors.map(|colors| Arc::new(vertices.iter().map(|v| colors[v.0]).collect()));
+        self
+    }
+}
+
+/// Crease angle `load` generates normals with, in degrees: wide enough to smooth over curved surfaces
+/// tessellated a bit coarsely, narrow enough to keep a box's edges sharp.
+pub const SMOOTHING_ANGLE: f32 = 60.;
+
+/// Material for geometry whose file doesn't give one: plain light grey.
+pub fn default_material() -> Material { Material::new(srgb(0.8, 0.8, 0.8), None, None, None, None) }
+
+/// Loads a model, picking the format by the file extension (.obj, .ply or .stl). Models whose file has no normals
+/// get them generated with `SMOOTHING_ANGLE`; the format modules' own `load`s leave them out.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Model> {
+    let path = path.as_ref();
+    let ext = path.extension().and_then(OsStr::to_str).map(str::to_ascii_lowercase).unwrap_or_default();
+    let model = match ext.as_str() {
+        "obj" => obj::load(path),
+        "ply" => ply::load(path),
+        "stl" => stl::load(path),
+        _ => Err(Error::Unsupported(format!("model format {:?}", ext)))
+    }?;
+    Ok(model.with_generated_normals(SMOOTHING_ANGLE))
+}
+
+/// Name for a model's only group: the file name without its extension.
+fn file_stem(path: &Path) -> String {
+    path.file_stem().and_then(OsStr::to_str).unwrap_or_default().to_string()
+}
+
+/// Splits a polygon into triangles by ear clipping, so concave faces come out right too.
+/// Returns indices into `points`, keeping the polygon's winding.
+pub fn triangulate(points: &[Vector3<f32>]) -> Vec<[usize; 3]> {
+    let n = points.len();
+    if n < 3 { return vec![]; }
+    if n == 3 { return vec![[0, 1, 2]]; }
+
+    // Newell's method gives the polygon's normal even when it isn't quite flat
+    let mut normal = Vector3::new(0., 0., 0.);
+    for i in 0..n {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        normal += Vector3::new((a.y - b.y) * (a.z + b.z), (a.z - b.z) * (a.x + b.x), (a.x - b.x) * (a.y + b.y));
+    }
+    let fan = || (1..n - 1).map(|i| [0, i, i + 1]).collect();
+    if normal.norm() == 0. { return fan(); }
+
+    let convex = |a: usize, b: usize, c: usize| (points[b] - points[a]).cross(&(points[c] - points[b])).dot(&normal) > 0.;
+    let inside = |p: &Vector3<f32>, a: usize, b: usize, c: usize| {
+        let edge = |u: usize, v: usize| (points[v] - points[u]).cross(&(p - points[u])).dot(&normal) >= 0.;
+        edge(a, b) && edge(b, c) && edge(c, a)
+    };
+
+    let mut remaining: Vec<usize> = (0..n).collect();
+    let mut triangles = Vec::with_capacity(n - 2);
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let ear = (0..m).find(|&i| {
+            let (a, b, c) = (remaining[(i + m - 1) % m], remaining[i], remaining[(i + 1) % m]);
+            convex(a, b, c) && !remaining.iter().any(|&p| p != a && p != b && p != c && inside(&points[p], a, b, c))
+        });
+        let i = match ear {
+            Some(i) => i,
+            None => return fan() // self-intersecting or degenerate, no ear to cut
+        };
+        triangles.push([remaining[(i + m - 1) % m], remaining[i], remaining[(i + 1) % m]]);
+        remaining.remove(i);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::{Vector2, Vector3, Vector4};
+use crate::error::{Error, Result};
+use crate::formats;
+use crate::framebuffer::Framebuffer;
+use crate::objects::{Material, MaterialQuality};
+use crate::texture::{ImageTexture, Wrap};
+use super::{default_material, triangulate, Group, Model};
+
+/// Reads a Wavefront OBJ file along with the MTL libraries it names, and the texture maps those name,
+/// all looked up next to it. Libraries that aren't there are skipped, leaving their materials undefined.
+pub fn load(path: &Path) -> Result<Model> {
+    let source = fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut materials = HashMap::new();
+    for line in source.lines() {
+        let mut words = line.split_whitespace();
+        if words.next() != Some("mtllib") { continue; }
+        for lib in words {
+            // OBJs often come without their MTL files; the geometry is still worth having
+            if let Ok(source) = fs::read_to_string(dir.join(lib)) { materials.extend(read_mtl(&source, dir)?); }
+        }
+    }
+    read(&source, &materials)
+}
+
+/// Parses OBJ source: `v`, `vt` and `vn` vertex data, `f` faces of any size (triangulated), `g`/`o` groups
+/// and `usemtl` materials, looked up in `materials`. Faces before any `usemtl`, or after one naming a material
+/// that isn't there, get `default_material`.
+/// Other statements are skipped.
+pub fn read(source: &str, materials: &HashMap<String, Material>) -> Result<Model> {
+    let (mut positions, mut uvs, mut normals) = (vec![], vec![], vec![]);
+    let mut vertices: Vec<(usize, Option<usize>, Option<usize>)> = vec![];
+    let mut vertex_index = HashMap::new();
+    let mut groups: Vec<(String, Option<String>, Vec<[usize; 3]>)> = vec![];
+    let (mut group, mut material): (String, Option<String>) = (String::new(), None);
+
+    for (n, line) in source.lines().enumerate() {
+        let err = |msg: String| Error::Format(format!("line {}: {}", n + 1, msg));
+        let line = line.split('#').next().unwrap_or("");
+        let mut words = line.split_whitespace();
+        let keyword = match words.next() {
+            Some(k) => k,
+            None => continue
+        };
+        let args: Vec<&str> = words.collect();
+        let numbers = || args.iter().map(|a| a.parse::<f32>().map_err(|_| err(format!("bad number {:?}", a)))).collect::<Result<Vec<f32>>>();
+
+        match keyword {
+            "v" | "vn" => {
+                let v = numbers()?;
+                if v.len() < 3 { return Err(err(format!("{} needs three coordinates", keyword))); }
+                let v = Vector3::new(v[0], v[1], v[2]);
+                if keyword == "v" { positions.push(v); } else { normals.push(v); }
+            }
+            "vt" => {
+                let v = numbers()?;
+                if v.is_empty() { return Err(err("vt needs a coordinate".to_string())); }
+                uvs.push(Vector2::new(v[0], v.get(1).cloned().unwrap_or(0.)));
+            }
+            "g" | "o" => group = args.join(" "),
+            "usemtl" => material = Some(args.join(" ")),
+            "f" => {
+                if args.len() < 3 { return Err(err("a face needs at least three vertices".to_string())); }
+                let mut face = Vec::with_capacity(args.len());
+                for a in &args {
+                    let mut refs = a.split('/');
+                    let mut index = |count: usize, required: bool| -> Result<Option<usize>> {
+                        match refs.next() {
+                            Some("") | None if !required => Ok(None),
+                            Some(r) => resolve(r, count).map(Some).ok_or_else(|| err(format!("bad vertex reference {:?}", a))),
+                            None => Err(err(format!("bad vertex reference {:?}", a)))
+                        }
+                    };
+                    let key = (index(positions.len(), true)?.unwrap(), index(uvs.len(), false)?, index(normals.len(), false)?);
+                    let next = vertices.len();
+                    face.push(*vertex_index.entry(key).or_insert_with(|| {
+                        vertices.push(key);
+                        next
+                    }));
+                }
+
+                let corners: Vec<Vector3<f32>> = face.iter().map(|&v| positions[vertices[v].0]).collect();
+                let triangles = triangulate(&corners).into_iter().map(|t| [face[t[0]], face[t[1]], face[t[2]]]);
+                match groups.iter_mut().find(|g| g.0 == group && g.1 == material) {
+                    Some(g) => g.2.extend(triangles),
+                    None => groups.push((group.clone(), material.clone(), triangles.collect()))
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // faces can mix references with and without texture coordinates or normals; the missing ones are zero
+    let has_uvs = vertices.iter().any(|v| v.1.is_some());
+    let has_normals = vertices.iter().any(|v| v.2.is_some());
+    Ok(Model {
+        positions: Arc::new(vertices.iter().map(|v| positions[v.0]).collect()),
+        uvs: if has_uvs { Some(Arc::new(vertices.iter().map(|v| v.1.map_or(Vector2::new(0., 0.), |i| uvs[i])).collect())) } else { None },
+        colors: None,
+        normals: if has_normals { Some(Arc::new(vertices.iter().map(|v| v.2.map_or(Vector3::new(0., 0., 0.), |i| normals[i].normalize())).collect())) } else { None },
+        groups: groups.into_iter().map(|(name, material, triangles)| Group {
+            name,
+            material: material.and_then(|m| materials.get(&m).cloned()).unwrap_or_else(default_material),
+            triangles
+        }).collect()
+    })
+}
+
+/// Turns a 1-based (or, counting back from the latest, negative) OBJ reference into an index.
+fn resolve(r: &str, count: usize) -> Option<usize> {
+    let i: i64 = r.parse().ok()?;
+    let index = if i < 0 { count as i64 + i } else { i - 1 };
+    if index >= 0 && (index as usize) < count { Some(index as usize) } else { None }
+}
+
+/// The MTL statements we understand, with their defaults.
+struct MtlEntry {
+    kd: Vector3<f32>,
+    ks: Vector3<f32>,
+    ns: f32,
+    ni: f32,
+    d: f32,
+    illum: u32,
+    map_kd: Option<ImageTexture>,
+    map_ks: Option<ImageTexture>,
+    map_refl: Option<ImageTexture>
+}
+
+impl MtlEntry {
+    /// `Kd` becomes the diffuse color and `Ks` (averaged) the specular albedo, `Ns` the specular exponent and
+    /// `Ni` the refractive index. `illum` 0-1 is matte, 2 adds highlights and 3 and up reflect by `Ks`; `d` below 1
+    /// lets the rest of the light through by refraction. `map_Kd`, `map_Ks` and `map_refl` vary the diffuse color,
+    /// highlights and reflections across the surface, each multiplied by the matching constant as the format says.
+    fn material(&self) -> Material {
+        let specular = (self.ks.x + self.ks.y + self.ks.z) / 3.;
+        let reflective = self.illum >= 3 && self.illum != 10; // 10 is the shadow-catcher model
+        let mut qualities = vec![if self.illum < 2 { MaterialQuality::Matte } else { MaterialQuality::Smooth }];
+        if reflective || self.d < 1. { qualities.push(MaterialQuality::Reflective); }
+        if self.d < 1. { qualities.push(MaterialQuality::Refractive); }
+        let albedo = Vector4::new(self.d, specular, if reflective { specular } else { 0. }, 1. - self.d);
+        let mut material = Material::new(self.kd, Some(albedo), Some(self.ni), Some(self.ns), Some(qualities));
+        if let Some(ref t) = self.map_kd { material = material.with_diffuse_texture(Arc::new(t.clone().with_tint(self.kd))); }
+        if let Some(ref t) = self.map_ks { material = material.with_specular_texture(Arc::new(t.clone())); }
+        if let Some(ref t) = self.map_refl { material = material.with_reflectivity_texture(Arc::new(t.clone())); }
+        material
+    }
+}
+
+impl Default for MtlEntry {
+    fn default() -> MtlEntry {
+        MtlEntry { kd: Vector3::new(0.8, 0.8, 0.8), ks: Vector3::new(0., 0., 0.), ns: 0., ni: 1., d: 1., illum: 2, map_kd: None, map_ks: None, map_refl: None }
+    }
+}
+
+/// Parses an MTL library into materials by name, loading texture maps from paths relative to `dir`.
+/// Colors are taken as linear, which is what exporters write.
+pub fn read_mtl(source: &str, dir: &Path) -> Result<HashMap<String, Material>> {
+    let mut entries: Vec<(String, MtlEntry)> = vec![];
+    let mut images: HashMap<PathBuf, Arc<Framebuffer>> = HashMap::new();
+    for (n, line) in source.lines().enumerate() {
+        let err = |msg: String| Error::Format(format!("line {}: {}", n + 1, msg));
+        let line = line.split('#').next().unwrap_or("");
+        let mut words = line.split_whitespace();
+        let keyword = match words.next() {
+            Some(k) => k,
+            None => continue
+        };
+        let args: Vec<&str> = words.collect();
+        if keyword == "newmtl" {
+            entries.push((args.join(" "), MtlEntry::default()));
+            continue;
+        }
+        let entry = match entries.last_mut() {
+            Some(e) => &mut e.1,
+            None => continue // statements before the first newmtl belong to nothing
+        };
+        let number = |i: usize| args.get(i).and_then(|a| a.parse::<f32>().ok()).ok_or_else(|| err(format!("{} expects a number", keyword)));
+        let color = || -> Result<Vector3<f32>> {
+            let r = number(0)?;
+            Ok(Vector3::new(r, number(1).unwrap_or(r), number(2).unwrap_or(r))) // a single value means grey
+        };
+        match keyword {
+            "Kd" => entry.kd = color()?,
+            "Ks" => entry.ks = color()?,
+            "Ns" => entry.ns = number(0)?,
+            "Ni" => entry.ni = number(0)?,
+            "d" => entry.d = number(0)?.clamp(0., 1.),
+            "Tr" => entry.d = 1. - number(0)?.clamp(0., 1.),
+            "illum" => entry.illum = number(0)? as u32,
+            "map_Kd" | "map_Ks" | "map_refl" => {
+                let texture = texture_map(&args, dir, &mut images).map_err(|e| match e {
+                    Error::Format(msg) => err(msg),
+                    e => e
+                })?;
+                match keyword {
+                    "map_Kd" => entry.map_kd = Some(texture),
+                    "map_Ks" => entry.map_ks = Some(texture),
+                    _ => entry.map_refl = Some(texture)
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(entries.into_iter().map(|(name, e)| (name, e.material())).collect())
+}
+
+/// Reads the arguments of a texture map statement: options, of which `-clamp` and `-s` (taken as the number of
+/// copies per unit of UV, as exporters write it) are used and the rest skipped, then the image's file name.
+/// Images already loaded for another map are shared.
+fn texture_map(args: &[&str], dir: &Path, images: &mut HashMap<PathBuf, Arc<Framebuffer>>) -> Result<ImageTexture> {
+    let (mut wrap, mut scale) = (Wrap::Repeat, Vector2::new(1., 1.));
+    let mut i = 0;
+    while i + 1 < args.len() && args[i].starts_with('-') {
+        let numbers: Vec<f32> = args[i + 1..].iter().take(3).map_while(|a| a.parse().ok()).collect();
+        i += match args[i] {
+            "-clamp" => {
+                wrap = if args[i + 1] == "on" { Wrap::Clamp } else { Wrap::Repeat };
+                2
+            }
+            "-s" | "-o" | "-t" => {
+                if numbers.is_empty() { return Err(Error::Format(format!("{} expects numbers", args[i]))); }
+                if args[i] == "-s" { scale = Vector2::new(numbers[0], numbers.get(1).cloned().unwrap_or(numbers[0])); }
+                1 + numbers.len()
+            }
+            "-mm" => 3,
+            _ => 2 // -blendu, -blendv, -bm, -boost, -cc, -imfchan and -texres take one value
+        };
+    }
+    let name = args.get(i..).unwrap_or_default().join(" ");
+    if name.is_empty() { return Err(Error::Format("texture map without a file name".to_string())); }
+
+    let path = dir.join(name);
+    let image = match images.get(&path) {
+        Some(image) => image.clone(),
+        None => {
+            let image = Arc::new(formats::load(&path)?);
+            images.insert(path, image.clone());
+            image
+        }
+    };
+    Ok(ImageTexture::new(image).with_wrap(wrap).with_scale(scale))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_materials_fall_back_to_the_default() {
+        let model = read("v 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl missing\nf 1 2 3\n", &HashMap::new()).unwrap();
+        assert_eq!(model.groups.len(), 1);
+        assert_eq!(model.groups[0].material.diffuse_color, default_material().diffuse_color);
+    }
+
+    #[test]
+    fn missing_mtl_library_keeps_the_geometry() {
+        let dir = std::env::temp_dir().join(format!("imgfun-obj-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("no_mtl.obj");
+        fs::write(&path, "mtllib gone.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n").unwrap();
+        let model = load(&path);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(model.unwrap().groups[0].triangles.len(), 1);
+    }
+
+    #[test]
+    fn reads_negative_indices_and_normal_only_references() {
+        let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 2\nf -3//-1 -2//-1 -1//-1\n";
+        let model = read(source, &HashMap::new()).unwrap();
+        assert_eq!(*model.positions, vec![Vector3::new(0., 0., 0.), Vector3::new(1., 0., 0.), Vector3::new(0., 1., 0.)]);
+        assert_eq!(model.groups[0].triangles, vec![[0, 1, 2]]);
+        assert!(model.uvs.is_none());
+        assert!(model.normals.unwrap().iter().all(|n| *n == Vector3::new(0., 0., 1.)));
+    }
+
+    #[test]
+    fn triangulates_concave_faces() {
+        // an L shape of area 3, whose reflex corner is at (1, 1)
+        let source = "v 0 0 0\nv 2 0 0\nv 2 1 0\nv 1 1 0\nv 1 2 0\nv 0 2 0\nf 1 2 3 4 5 6\n";
+        let model = read(source, &HashMap::new()).unwrap();
+        let triangles = &model.groups[0].triangles;
+        assert_eq!(triangles.len(), 4);
+        let p = &model.positions;
+        let area: f32 = triangles.iter().map(|t| (p[t[1]] - p[t[0]]).cross(&(p[t[2]] - p[t[0]])).z / 2.).sum();
+        assert!(triangles.iter().all(|t| (p[t[1]] - p[t[0]]).cross(&(p[t[2]] - p[t[0]])).z > 0.), "a triangle is flipped");
+        assert!((area - 3.).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,276 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use crate::{Vector2, Vector3};
+use crate::color::srgb_to_linear;
+use crate::error::{Error, Result};
+use super::{default_material, file_stem, triangulate, Group, Model};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Encoding {
+    Ascii,
+    LittleEndian,
+    BigEndian
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Type {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64
+}
+
+impl Type {
+    fn parse(name: &str) -> Result<Type> {
+        match name {
+            "char" | "int8" => Ok(Type::I8),
+            "uchar" | "uint8" => Ok(Type::U8),
+            "short" | "int16" => Ok(Type::I16),
+            "ushort" | "uint16" => Ok(Type::U16),
+            "int" | "int32" => Ok(Type::I32),
+            "uint" | "uint32" => Ok(Type::U32),
+            "float" | "float32" => Ok(Type::F32),
+            "double" | "float64" => Ok(Type::F64),
+            _ => Err(Error::Format(format!("unknown property type {}", name)))
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Type::I8 | Type::U8 => 1,
+            Type::I16 | Type::U16 => 2,
+            Type::I32 | Type::U32 | Type::F32 => 4,
+            Type::F64 => 8
+        }
+    }
+
+    /// What a color channel of this type reads at full intensity.
+    fn full_scale(self) -> f64 {
+        match self {
+            Type::I8 => 127.,
+            Type::U8 => 255.,
+            Type::I16 => 32767.,
+            Type::U16 => 65535.,
+            _ => 1.
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    ty: Type,
+    /// type of the length prefix for list properties
+    count: Option<Type>
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>
+}
+
+/// Reads a PLY file (ASCII or binary). See `read`.
+pub fn load(path: &Path) -> Result<Model> {
+    let mut model = read(&fs::read(path)?)?;
+    for g in &mut model.groups { g.name = file_stem(path); }
+    Ok(model)
+}
+
+/// Parses PLY data: the `vertex` element's `x`/`y`/`z` with optional normals (`nx`/`ny`/`nz`), texture coordinates
+/// (`u`/`v`, `s`/`t` or `texture_u`/`texture_v`) and colors (`red`/`green`/`blue`, decoded from sRGB),
+/// and the `face` element's `vertex_indices` lists, triangulated. Other elements and properties are skipped.
+pub fn read(bytes: &[u8]) -> Result<Model> {
+    let (encoding, elements, body) = header(bytes)?;
+    let mut values = Values { encoding, bytes: body, pos: 0 };
+
+    let mut positions = vec![];
+    let (mut normals, mut uvs, mut colors) = (vec![], vec![], vec![]);
+    let mut faces: Vec<Vec<usize>> = vec![];
+    for element in &elements {
+        let find = |names: &[&str]| names.iter().find_map(|n| element.properties.iter().position(|p| p.name == *n && p.count.is_none()));
+        let xyz = [find(&["x"]), find(&["y"]), find(&["z"])];
+        let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+        let uv = [find(&["u", "s", "texture_u"]), find(&["v", "t", "texture_v"])];
+        let rgb = [find(&["red", "diffuse_red"]), find(&["green", "diffuse_green"]), find(&["blue", "diffuse_blue"])];
+        let indices = element.properties.iter().position(|p| (p.name == "vertex_indices" || p.name == "vertex_index") && p.count.is_some());
+
+        for _ in 0..element.count {
+            let mut scalars = vec![0f64; element.properties.len()];
+            let mut list = vec![];
+            for (i, p) in element.properties.iter().enumerate() {
+                match p.count {
+                    Some(count_type) => {
+                        let n = values.next(count_type)?;
+                        if n < 0. { return Err(Error::Format("negative list length".to_string())); }
+                        let items = (0..n as usize).map(|_| values.next(p.ty)).collect::<Result<Vec<f64>>>()?;
+                        if Some(i) == indices { list = items; }
+                    }
+                    None => scalars[i] = values.next(p.ty)?
+                }
+            }
+
+            let get = |i: Option<usize>| i.map(|i| scalars[i] as f32);
+            if element.name == "vertex" {
+                let (x, y, z) = match xyz {
+                    [Some(x), Some(y), Some(z)] => (scalars[x] as f32, scalars[y] as f32, scalars[z] as f32),
+                    _ => return Err(Error::Format("vertices need x, y and z".to_string()))
+                };
+                positions.push(Vector3::new(x, y, z));
+                if let [Some(x), Some(y), Some(z)] = normal.map(get) { normals.push(Vector3::new(x, y, z).normalize()); }
+                if let [Some(u), Some(v)] = uv.map(get) { uvs.push(Vector2::new(u, v)); }
+                if let [Some(r), Some(g), Some(b)] = rgb {
+                    let channel = |i: usize| srgb_to_linear((scalars[i] / element.properties[i].ty.full_scale()) as f32);
+                    colors.push(Vector3::new(channel(r), channel(g), channel(b)));
+                }
+            } else if element.name == "face" && indices.is_some() {
+                faces.push(list.into_iter().map(|i| i as usize).collect());
+            }
+        }
+    }
+
+    let mut triangles = vec![];
+    for face in &faces {
+        if face.iter().any(|&i| i >= positions.len()) {
+            return Err(Error::Format(format!("face refers to vertex {} of {}", face.iter().max().unwrap(), positions.len())));
+        }
+        let corners: Vec<Vector3<f32>> = face.iter().map(|&i| positions[i]).collect();
+        triangles.extend(triangulate(&corners).into_iter().map(|t| [face[t[0]], face[t[1]], face[t[2]]]));
+    }
+
+    let complete = |n: usize| n == positions.len() && n > 0;
+    let mut material = default_material();
+    if complete(colors.len()) { material.diffuse_color = Vector3::new(1., 1., 1.); } // the colors are the whole diffuse color
+    Ok(Model {
+        normals: if complete(normals.len()) { Some(Arc::new(normals)) } else { None },
+        uvs: if complete(uvs.len()) { Some(Arc::new(uvs)) } else { None },
+        colors: if complete(colors.len()) { Some(Arc::new(colors)) } else { None },
+        positions: Arc::new(positions),
+        groups: if triangles.is_empty() { vec![] } else { vec![Group { name: String::new(), material, triangles }] }
+    })
+}
+
+/// Splits the data into the header's description and the body that follows it.
+fn header(bytes: &[u8]) -> Result<(Encoding, Vec<Element>, &[u8])> {
+    let end = bytes.windows(10).position(|w| w == b"end_header")
+        .ok_or_else(|| Error::Format("no end_header".to_string()))?;
+    let body_start = bytes[end..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |n| end + n + 1);
+    let text = String::from_utf8_lossy(&bytes[..end]);
+
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some("ply") { return Err(Error::Format("not a PLY file".to_string())); }
+    let mut encoding = None;
+    let mut elements: Vec<Element> = vec![];
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", format, ..] => encoding = Some(match *format {
+                "ascii" => Encoding::Ascii,
+                "binary_little_endian" => Encoding::LittleEndian,
+                "binary_big_endian" => Encoding::BigEndian,
+                _ => return Err(Error::Unsupported(format!("PLY format {}", format)))
+            }),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| Error::Format(format!("bad element count {}", count)))?,
+                properties: vec![]
+            }),
+            ["property", "list", count, ty, name] => match elements.last_mut() {
+                Some(e) => e.properties.push(Property { name: name.to_string(), ty: Type::parse(ty)?, count: Some(Type::parse(count)?) }),
+                None => return Err(Error::Format("property before any element".to_string()))
+            },
+            ["property", ty, name] => match elements.last_mut() {
+                Some(e) => e.properties.push(Property { name: name.to_string(), ty: Type::parse(ty)?, count: None }),
+                None => return Err(Error::Format("property before any element".to_string()))
+            },
+            _ => {} // comments, obj_info and blank lines
+        }
+    }
+    let encoding = encoding.ok_or_else(|| Error::Format("no format line".to_string()))?;
+    Ok((encoding, elements, &bytes[body_start..]))
+}
+
+/// Reads property values one at a time from the body, whichever way it is encoded.
+struct Values<'a> {
+    encoding: Encoding,
+    bytes: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Values<'a> {
+    fn next(&mut self, ty: Type) -> Result<f64> {
+        if self.encoding == Encoding::Ascii {
+            while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace() { self.pos += 1; }
+            let start = self.pos;
+            while self.pos < self.bytes.len() && !self.bytes[self.pos].is_ascii_whitespace() { self.pos += 1; }
+            if start == self.pos { return Err(Error::Format("unexpected end of file".to_string())); }
+            let token = String::from_utf8_lossy(&self.bytes[start..self.pos]);
+            return token.parse().map_err(|_| Error::Format(format!("bad number {:?}", token)));
+        }
+
+        let size = ty.size();
+        if self.pos + size > self.bytes.len() { return Err(Error::Format("unexpected end of file".to_string())); }
+        let mut raw = [0u8; 8];
+        raw[..size].copy_from_slice(&self.bytes[self.pos..self.pos + size]);
+        if self.encoding == Encoding::BigEndian { raw[..size].reverse(); }
+        self.pos += size;
+        let (b2, b4) = ([raw[0], raw[1]], [raw[0], raw[1], raw[2], raw[3]]);
+        Ok(match ty {
+            Type::I8 => raw[0] as i8 as f64,
+            Type::U8 => raw[0] as f64,
+            Type::I16 => i16::from_le_bytes(b2) as f64,
+            Type::U16 => u16::from_le_bytes(b2) as f64,
+            Type::I32 => i32::from_le_bytes(b4) as f64,
+            Type::U32 => u32::from_le_bytes(b4) as f64,
+            Type::F32 => f32::from_le_bytes(b4) as f64,
+            Type::F64 => f64::from_le_bytes(raw)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_ascii_with_uchar_colors() {
+        let source = "ply\nformat ascii 1.0\ncomment a red, green and grey triangle\nelement vertex 3\n\
+            property float x\nproperty float y\nproperty float z\n\
+            property uchar red\nproperty uchar green\nproperty uchar blue\n\
+            element face 1\nproperty list uchar int vertex_indices\nend_header\n\
+            0 0 0 255 0 0\n1 0 0 0 255 0\n0 1 0 128 128 128\n3 0 1 2\n";
+        let model = read(source.as_bytes()).unwrap();
+        assert_eq!(*model.positions, vec![Vector3::new(0., 0., 0.), Vector3::new(1., 0., 0.), Vector3::new(0., 1., 0.)]);
+        assert_eq!(model.groups[0].triangles, vec![[0, 1, 2]]);
+        let colors = model.colors.unwrap();
+        assert_eq!(colors[0], Vector3::new(1., 0., 0.));
+        assert_eq!(colors[1], Vector3::new(0., 1., 0.));
+        let grey = srgb_to_linear(128. / 255.);
+        assert_eq!(colors[2], Vector3::new(grey, grey, grey));
+    }
+
+    #[test]
+    fn reads_big_endian_binary() {
+        let mut bytes = b"ply\nformat binary_big_endian 1.0\nelement vertex 4\n\
+            property float x\nproperty float y\nproperty float z\n\
+            element face 1\nproperty list uchar int vertex_indices\nend_header\n".to_vec();
+        for p in &[[0f32, 0., 0.], [2., 0., 0.], [2., 1., 0.], [0., 1., 0.]] {
+            for c in p { bytes.extend_from_slice(&c.to_be_bytes()); }
+        }
+        bytes.push(4);
+        for i in 0..4i32 { bytes.extend_from_slice(&i.to_be_bytes()); }
+
+        let model = read(&bytes).unwrap();
+        assert_eq!(model.positions[2], Vector3::new(2., 1., 0.));
+        let triangles = &model.groups[0].triangles;
+        assert_eq!(triangles.len(), 2);
+        assert!(triangles.iter().flatten().all(|&i| i < 4));
+        assert!(model.colors.is_none() && model.normals.is_none());
+    }
+}
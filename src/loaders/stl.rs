@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use crate::Vector3;
+use crate::error::{Error, Result};
+use super::{default_material, file_stem, Group, Model};
+
+/// Reads an STL file, ASCII or binary. See `read`.
+pub fn load(path: &Path) -> Result<Model> {
+    let mut model = read(&fs::read(path)?)?;
+    for g in &mut model.groups {
+        if g.name.is_empty() { g.name = file_stem(path); }
+    }
+    Ok(model)
+}
+
+/// Parses STL data. Binary files are told apart from ASCII ones by their size, since some binary exporters start
+/// the header with `solid` too. Corners at the same spot are merged into one vertex, and each ASCII `solid` becomes
+/// a group. The stored facet normals are ignored: the triangles' winding already says which way they face.
+pub fn read(bytes: &[u8]) -> Result<Model> {
+    let binary_size = |n: u32| 84 + 50 * n as usize;
+    let is_binary = bytes.len() >= 84 && bytes.len() == binary_size(u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]));
+    let mut welder = Welder::default();
+
+    let groups = if is_binary {
+        let triangles = bytes[84..].chunks_exact(50).map(|facet| {
+            let coord = |i: usize| f32::from_le_bytes([facet[i], facet[i + 1], facet[i + 2], facet[i + 3]]);
+            let mut corner = |c: usize| welder.vertex(Vector3::new(coord(12 + 12 * c), coord(16 + 12 * c), coord(20 + 12 * c)));
+            [corner(0), corner(1), corner(2)]
+        }).collect();
+        vec![Group { name: String::new(), material: default_material(), triangles }]
+    } else {
+        let text = String::from_utf8_lossy(bytes);
+        if !text.trim_start().starts_with("solid") { return Err(Error::Format("not an STL file".to_string())); }
+        let mut groups = vec![];
+        let mut corners = vec![];
+        for (n, line) in text.lines().enumerate() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.first() {
+                Some(&"solid") => groups.push(Group { name: words[1..].join(" "), material: default_material(), triangles: vec![] }),
+                Some(&"vertex") => {
+                    let coords = words[1..].iter().map(|w| w.parse::<f32>()).collect::<std::result::Result<Vec<f32>, _>>();
+                    match coords {
+                        Ok(ref c) if c.len() == 3 => corners.push(welder.vertex(Vector3::new(c[0], c[1], c[2]))),
+                        _ => return Err(Error::Format(format!("line {}: bad vertex", n + 1)))
+                    }
+                }
+                Some(&"endfacet") => {
+                    if corners.len() != 3 { return Err(Error::Format(format!("line {}: a facet needs three vertices", n + 1))); }
+                    match groups.last_mut() {
+                        Some(g) => g.triangles.push([corners[0], corners[1], corners[2]]),
+                        None => return Err(Error::Format("facet outside of a solid".to_string()))
+                    }
+                    corners.clear();
+                }
+                _ => {}
+            }
+        }
+        groups.retain(|g| !g.triangles.is_empty());
+        groups
+    };
+
+    Ok(Model { positions: Arc::new(welder.positions), normals: None, uvs: None, colors: None, groups })
+}
+
+/// Hands out one index per distinct position.
+#[derive(Default)]
+struct Welder {
+    positions: Vec<Vector3<f32>>,
+    index: HashMap<[u32; 3], usize>
+}
+
+impl Welder {
+    fn vertex(&mut self, p: Vector3<f32>) -> usize {
+        let positions = &mut self.positions;
+        *self.index.entry([p.x.to_bits(), p.y.to_bits(), p.z.to_bits()]).or_insert_with(|| {
+            positions.push(p);
+            positions.len() - 1
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary(header: &[u8], facets: &[[[f32; 3]; 3]]) -> Vec<u8> {
+        let mut bytes = header.to_vec();
+        bytes.resize(80, b' ');
+        bytes.extend_from_slice(&(facets.len() as u32).to_le_bytes());
+        for f in facets {
+            bytes.extend_from_slice(&[0; 12]); // the normal, which is ignored
+            for c in f.iter().flatten() { bytes.extend_from_slice(&c.to_le_bytes()); }
+            bytes.extend_from_slice(&[0; 2]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn reads_ascii() {
+        let source = "solid square\n\
+            facet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 1 1 0\nendloop\nendfacet\n\
+            facet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 1 0\nvertex 0 1 0\nendloop\nendfacet\n\
+            endsolid square\n";
+        let model = read(source.as_bytes()).unwrap();
+        assert_eq!(model.positions.len(), 4);
+        assert_eq!(model.groups.len(), 1);
+        assert_eq!(model.groups[0].name, "square");
+        assert_eq!(model.groups[0].triangles, vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn reads_binary_even_when_it_starts_with_solid() {
+        let facets = [[[0., 0., 0.], [1., 0., 0.], [1., 1., 0.]], [[0., 0., 0.], [1., 1., 0.], [0., 1., 0.]]];
+        for header in &[&b"binary STL"[..], &b"solid exported by a binary writer"[..]] {
+            let model = read(&binary(header, &facets)).unwrap();
+            assert_eq!(model.positions.len(), 4);
+            assert_eq!(model.positions[2], Vector3::new(1., 1., 0.));
+            assert_eq!(model.groups[0].triangles, vec![[0, 1, 2], [0, 2, 3]]);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::sync::Arc;
+use crate::{Vector2, Vector3};
+use crate::bvh::{Aabb, Bvh};
+use crate::objects::{intersect_triangle, HitRecord, Material, Shape};
+
+/// Triangles given as indices into a vertex buffer. The buffers sit behind `Arc`s so several meshes
+/// (say the parts of a model that use different materials) can share them without copying.
+/// The triangles get their own BVH, so the scene's BVH sees the whole mesh as a single shape.
+pub struct TriangleMesh {
+    positions: Arc<Vec<Vector3<f32>>>,
+    normals: Option<Arc<Vec<Vector3<f32>>>>,
+    uvs: Option<Arc<Vec<Vector2<f32>>>>,
+    colors: Option<Arc<Vec<Vector3<f32>>>>,
+    triangles: Vec<[usize; 3]>,
+    pub material: Material,
+    bvh: Bvh
+}
+
+impl TriangleMesh {
+    /// Panics if a triangle refers to a vertex past the end of `positions`.
+    pub fn new(positions: Arc<Vec<Vector3<f32>>>, triangles: Vec<[usize; 3]>, m: Material) -> TriangleMesh {
+        assert!(triangles.iter().flatten().all(|&i| i < positions.len()), "triangle index out of range of the vertex buffer");
+        let bvh = Bvh::new(triangles.iter().enumerate()
+            .map(|(n, t)| (n, Aabb::from_points(t.iter().map(|&i| &positions[i]))))
+            .collect());
+        TriangleMesh { positions, normals: None, uvs: None, colors: None, triangles, material: m, bvh }
+    }
+
+    /// Normals for each vertex, indexed the same way as the positions, blended across the triangles for smooth shading.
+    /// Panics if there are fewer of them than vertices.
+    pub fn with_normals(mut self, normals: Arc<Vec<Vector3<f32>>>) -> TriangleMesh {
+        assert!(normals.len() >= self.positions.len(), "fewer normals than vertices");
+        self.normals = Some(normals);
+        self
+    }
+
+    /// Texture coordinates for each vertex, indexed the same way as the positions.
+    /// Panics if there are fewer of them than vertices.
+    pub fn with_uvs(mut self, uvs: Arc<Vec<Vector2<f32>>>) -> TriangleMesh {
+        assert!(uvs.len() >= self.positions.len(), "fewer texture coordinates than vertices");
+        self.uvs = Some(uvs);
+        self
+    }
+
+    /// Linear colors for each vertex, blended across the triangles and multiplied into the material's diffuse color.
+    /// Panics if there are fewer of them than vertices.
+    pub fn with_colors(mut self, colors: Arc<Vec<Vector3<f32>>>) -> TriangleMesh {
+        assert!(colors.len() >= self.positions.len(), "fewer colors than vertices");
+        self.colors = Some(colors);
+        self
+    }
+
+    pub fn positions(&self) -> &Arc<Vec<Vector3<f32>>> { &self.positions }
+
+    pub fn normals(&self) -> Option<&Arc<Vec<Vector3<f32>>>> { self.normals.as_ref() }
+
+    pub fn uvs(&self) -> Option<&Arc<Vec<Vector2<f32>>>> { self.uvs.as_ref() }
+
+    pub fn colors(&self) -> Option<&Arc<Vec<Vector3<f32>>>> { self.colors.as_ref() }
+
+    pub fn triangles(&self) -> &[[usize; 3]] { &self.triangles }
+
+    fn vertices(&self, triangle: usize) -> [&Vector3<f32>; 3] {
+        let [a, b, c] = self.triangles[triangle];
+        [&self.positions[a], &self.positions[b], &self.positions[c]]
+    }
+}
+
+impl Shape for TriangleMesh {
+    fn ray_intersect(&self, orig: &Vector3<f32>, dir: &Vector3<f32>) -> Option<HitRecord<'_>> {
+        let (n, t, u, v) = self.bvh.closest(orig, dir, f32::INFINITY, |n| {
+            let [a, b, c] = self.vertices(n);
+            intersect_triangle(orig, dir, a, b, c).map(|(t, u, v)| (t, (n, t, u, v)))
+        })?;
+        let [a, b, c] = self.vertices(n);
+        let normal = (b - a).cross(&(c - a)).normalize();
+        let [ia, ib, ic] = self.triangles[n];
+        let uv = match self.uvs {
+            Some(ref uvs) => uvs[ia] * (1. - u - v) + uvs[ib] * u + uvs[ic] * v,
+            None => Vector2::new(u, v)
+        };
+        let mut hit = HitRecord::new(orig, dir, t, normal, uv, &self.material);
+        hit.color = self.colors.as_ref().map(|c| c[ia] * (1. - u - v) + c[ib] * u + c[ic] * v);
+        if let Some(ref normals) = self.normals {
+            let smooth = normals[ia] * (1. - u - v) + normals[ib] * u + normals[ic] * v;
+            if smooth.norm() > 0. { hit = hit.with_shading_normal(smooth.normalize()); } // vertices without a normal are left zero
+        }
+        Some(hit)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> { self.bvh.bounds() }
+}
@@ -0,0 +1,133 @@
+use crate::Vector3;
+use crate::sampling::Rng;
+
+/// Which smooth noise function `Noise` builds its patterns from.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Basis {
+    /// Ken Perlin's improved gradient noise, on a cubic grid
+    Perlin,
+    /// gradient noise on a grid of tetrahedra, cheaper and without the cubic grid's axis-aligned look
+    Simplex
+}
+
+/// Smooth pseudo-random values over 3D space, roughly in [-1, 1] and zero at every lattice point.
+/// The lattice's gradients come from a permutation table shuffled by the seed, so each seed gives its own pattern
+/// and the same seed always gives the same one.
+#[derive(Clone)]
+pub struct Noise {
+    pub basis: Basis,
+    perm: Vec<u8>
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Noise {
+        let mut perm: Vec<u8> = (0..=255).collect();
+        let mut rng = Rng::new(seed, 0xda3e_39cb_94b9_5bdb);
+        for i in (1..256).rev() { // Fisher-Yates
+            perm.swap(i, rng.next_u32() as usize % (i + 1));
+        }
+        perm.extend_from_within(..); // doubled so hashing a corner never has to wrap
+        Noise { basis: Basis::Perlin, perm }
+    }
+
+    pub fn with_basis(mut self, basis: Basis) -> Noise {
+        self.basis = basis;
+        self
+    }
+
+    /// One octave of the chosen basis.
+    pub fn value(&self, p: &Vector3<f32>) -> f32 {
+        match self.basis {
+            Basis::Perlin => self.perlin(p),
+            Basis::Simplex => self.simplex(p)
+        }
+    }
+
+    /// Fractal Brownian motion: `octaves` layers of noise, each at twice the frequency and half the strength of the
+    /// one before, scaled back into [-1, 1]. More octaves add finer detail.
+    pub fn fbm(&self, p: &Vector3<f32>, octaves: u32) -> f32 {
+        self.octaves(p, octaves, |n| n)
+    }
+
+    /// Like `fbm` but summing the absolute value of each layer, which gives billowy, creased patterns in [0, 1].
+    pub fn turbulence(&self, p: &Vector3<f32>, octaves: u32) -> f32 {
+        self.octaves(p, octaves, f32::abs)
+    }
+
+    fn octaves<F: Fn(f32) -> f32>(&self, p: &Vector3<f32>, octaves: u32, f: F) -> f32 {
+        let (mut sum, mut total, mut amplitude, mut frequency) = (0., 0., 1., 1.);
+        for _ in 0..octaves.max(1) {
+            sum += f(self.value(&(p * frequency))) * amplitude;
+            total += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.;
+        }
+        sum / total
+    }
+
+    fn hash(&self, x: i64, y: i64, z: i64) -> usize {
+        let p = |i: usize| self.perm[i] as usize;
+        p(p(p((x & 255) as usize) + (y & 255) as usize) + (z & 255) as usize)
+    }
+
+    /// Improved Perlin noise (2002): a gradient picked from twelve cube edge directions at each lattice point,
+    /// blended with a quintic curve so the result is smooth through its second derivative.
+    pub fn perlin(&self, p: &Vector3<f32>) -> f32 {
+        let cell = p.map(f32::floor);
+        let f = p - cell;
+        let (x, y, z) = (cell.x as i64, cell.y as i64, cell.z as i64);
+        let fade = |t: f32| t * t * t * (t * (t * 6. - 15.) + 10.);
+        let (u, v, w) = (fade(f.x), fade(f.y), fade(f.z));
+        let corner = |dx: i64, dy: i64, dz: i64| {
+            gradient(self.hash(x + dx, y + dy, z + dz), f.x - dx as f32, f.y - dy as f32, f.z - dz as f32)
+        };
+        let lerp = |t: f32, a: f32, b: f32| a + t * (b - a);
+        lerp(w,
+            lerp(v, lerp(u, corner(0, 0, 0), corner(1, 0, 0)), lerp(u, corner(0, 1, 0), corner(1, 1, 0))),
+            lerp(v, lerp(u, corner(0, 0, 1), corner(1, 0, 1)), lerp(u, corner(0, 1, 1), corner(1, 1, 1))))
+    }
+
+    /// 3D simplex noise, after Stefan Gustavson's explanation of Perlin's design: the four corners of the
+    /// tetrahedron holding the point each add a gradient that falls off with distance.
+    pub fn simplex(&self, p: &Vector3<f32>) -> f32 {
+        const SKEW: f32 = 1. / 3.;
+        const UNSKEW: f32 = 1. / 6.;
+        let s = (p.x + p.y + p.z) * SKEW;
+        let cell = (p + Vector3::new(s, s, s)).map(f32::floor);
+        let t = (cell.x + cell.y + cell.z) * UNSKEW;
+        let d0 = p - (cell - Vector3::new(t, t, t));
+
+        // which of the six tetrahedra in the skewed cube: step along the axes from largest offset to smallest
+        let (first, second) = if d0.x >= d0.y {
+            if d0.y >= d0.z { ([1, 0, 0], [1, 1, 0]) }
+            else if d0.x >= d0.z { ([1, 0, 0], [1, 0, 1]) }
+            else { ([0, 0, 1], [1, 0, 1]) }
+        } else if d0.y < d0.z { ([0, 0, 1], [0, 1, 1]) }
+        else if d0.x < d0.z { ([0, 1, 0], [0, 1, 1]) }
+        else { ([0, 1, 0], [1, 1, 0]) };
+
+        let (x, y, z) = (cell.x as i64, cell.y as i64, cell.z as i64);
+        let mut sum = 0.;
+        for (n, offset) in [[0, 0, 0], first, second, [1, 1, 1]].iter().enumerate() {
+            let d = d0 - Vector3::new(offset[0] as f32, offset[1] as f32, offset[2] as f32) + Vector3::new(1., 1., 1.) * (n as f32 * UNSKEW);
+            let falloff = 0.6 - d.norm_squared();
+            if falloff > 0. {
+                let g = gradient(self.hash(x + offset[0], y + offset[1], z + offset[2]), d.x, d.y, d.z);
+                sum += falloff.powi(4) * g;
+            }
+        }
+        32. * sum
+    }
+}
+
+impl Default for Noise {
+    fn default() -> Noise { Noise::new(0) }
+}
+
+/// Dot product of (x, y, z) with one of the twelve directions to the edges of a cube, chosen by the hash.
+fn gradient(hash: usize, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 { y } else if h == 12 || h == 14 { x } else { z };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
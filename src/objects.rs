@@ -0,0 +1,329 @@
+use crate::bvh::Aabb;
+use crate::render_funcs::{cast_ray, reflect, refract};
+use crate::scene::Scene;
+use std::f32;
+use std::sync::Arc;
+use crate::texture::Texture;
+use crate::{Vector2, Vector3, Vector4};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MaterialQuality {
+    Matte,
+    Smooth,
+    Reflective,
+    Refractive
+}
+
+#[derive(Clone)]
+pub struct Material {
+    pub albedo: Vector4<f32>,
+    pub refractive_index: f32,
+    /// linear; colors picked in sRGB should go through `color::srgb` first
+    pub diffuse_color: Vector3<f32>,
+    pub specular_exponent: f32,
+    pub qualities: Vec<MaterialQuality>,
+    /// overrides `diffuse_color` across the surface when set
+    pub diffuse_texture: Option<Arc<dyn Texture>>,
+    /// scales the highlights (`albedo[1]`) across the surface, per channel
+    pub specular_texture: Option<Arc<dyn Texture>>,
+    /// scales the reflections (`albedo[2]`) across the surface, per channel
+    pub reflectivity_texture: Option<Arc<dyn Texture>>
+}
+
+impl Material {
+    pub fn new(color: Vector3<f32>, a: Option<Vector4<f32>>, r: Option<f32>, spec: Option<f32>, q: Option<Vec<MaterialQuality>>) -> Material {
+        let a = a.unwrap_or(Vector4::new(0.,0.,0.,0.));
+        let r = r.unwrap_or(0.);
+        let spec = spec.unwrap_or(0.);
+        let q = q.unwrap_or(vec![MaterialQuality::Matte]);
+        Material { albedo: a, refractive_index: r, diffuse_color: color, specular_exponent: spec, qualities: q, diffuse_texture: None, specular_texture: None, reflectivity_texture: None }
+    }
+
+    pub fn with_diffuse_texture(mut self, texture: Arc<dyn Texture>) -> Material {
+        self.diffuse_texture = Some(texture);
+        self
+    }
+
+    pub fn with_specular_texture(mut self, texture: Arc<dyn Texture>) -> Material {
+        self.specular_texture = Some(texture);
+        self
+    }
+
+    pub fn with_reflectivity_texture(mut self, texture: Arc<dyn Texture>) -> Material {
+        self.reflectivity_texture = Some(texture);
+        self
+    }
+
+    pub fn diffuse_color_at(&self, uv: &Vector2<f32>, point: &Vector3<f32>) -> Vector3<f32> {
+        match self.diffuse_texture {
+            Some(ref t) => t.value(uv, point),
+            None => self.diffuse_color
+        }
+    }
+
+    /// How strongly highlights show at this spot, per channel.
+    pub fn specular_at(&self, uv: &Vector2<f32>, point: &Vector3<f32>) -> Vector3<f32> {
+        match self.specular_texture {
+            Some(ref t) => t.value(uv, point) * self.albedo[1],
+            None => Vector3::new(1., 1., 1.) * self.albedo[1]
+        }
+    }
+
+    /// How much of the reflected ray's color comes back from this spot, per channel.
+    pub fn reflectivity_at(&self, uv: &Vector2<f32>, point: &Vector3<f32>) -> Vector3<f32> {
+        match self.reflectivity_texture {
+            Some(ref t) => t.value(uv, point) * self.albedo[2],
+            None => Vector3::new(1., 1., 1.) * self.albedo[2]
+        }
+    }
+
+    pub fn has_quality(&self, qual: MaterialQuality) -> bool { self.qualities.contains(&qual) }
+
+    pub fn lit_surface_color(&self, I: &Vector3<f32>, N: &Vector3<f32>, intensity: f32, dir: Option<&Vector3<f32>>) -> Vector3<f32> {
+        let diffuse_light_intensity = 0f32.max(I.dot(N)) * intensity;
+        let mut color = self.diffuse_color * diffuse_light_intensity;
+        if let Some(d) = dir {
+            let specular_light_intensity = 0f32.max(-reflect(&-I, N).dot(d)).powf(self.specular_exponent) * intensity;
+            color = color * self.albedo[0] + Vector3::new(1.,1.,1.) * specular_light_intensity * self.albedo[1];
+        }
+        color
+    }
+
+    pub fn surface_quality(&self, dir: &Vector3<f32>, hit: &HitRecord, scene: &Scene, depth: i32) -> Vector3<f32> {
+        let mut color = Vector3::new(0., 0., 0.);
+        let N = hit.normal;
+        if self.has_quality(MaterialQuality::Reflective) {
+            let reflect_dir = reflect(dir, &N);
+            let reflect_color = cast_ray(&hit.offset_origin(&reflect_dir, scene.epsilon), &reflect_dir, scene, Some(depth - 1));
+            color += reflect_color.component_mul(&self.reflectivity_at(&hit.uv, &hit.point));
+        }
+        if self.has_quality(MaterialQuality::Refractive) {
+            let refract_dir = refract(dir, &N, &self.refractive_index, &1.).normalize();
+            let refract_color = cast_ray(&hit.offset_origin(&refract_dir, scene.epsilon), &refract_dir, scene, Some(depth - 1));
+            color += refract_color * self.albedo[3];
+        }
+        color
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Light {
+    pub position: Vector3<f32>,
+    pub intensity: f32,
+    /// linear, white unless set
+    pub color: Vector3<f32>
+}
+
+impl Light {
+    pub fn new(p: &Vector3<f32>, i: &f32) -> Light {
+        Light { position: *p, intensity: *i, color: Vector3::new(1., 1., 1.) }
+    }
+
+    pub fn with_color(mut self, color: Vector3<f32>) -> Light {
+        self.color = color;
+        self
+    }
+}
+
+/// Everything `cast_ray` needs to know about the closest hit along a ray.
+pub struct HitRecord<'a> {
+    pub distance: f32,
+    pub point: Vector3<f32>,
+    /// outward facing shading normal, which smooth meshes blend across their triangles
+    pub normal: Vector3<f32>,
+    /// normal of the surface as it really is at the hit, which new rays start off from
+    pub geometric_normal: Vector3<f32>,
+    pub uv: Vector2<f32>,
+    pub material: &'a Material,
+    /// false when the ray started inside the shape and hit it from the back
+    pub front_face: bool,
+    /// color carried by the shape itself (such as interpolated vertex colors), multiplied into the material's diffuse color
+    pub color: Option<Vector3<f32>>
+}
+
+impl<'a> HitRecord<'a> {
+    pub fn new(orig: &Vector3<f32>, dir: &Vector3<f32>, distance: f32, normal: Vector3<f32>, uv: Vector2<f32>, material: &'a Material) -> HitRecord<'a> {
+        HitRecord { distance, point: orig + dir * distance, normal, geometric_normal: normal, uv, material, front_face: dir.dot(&normal) < 0., color: None }
+    }
+
+    /// Shades with `normal` while keeping the geometric normal for offsets. When the two disagree (normals written
+    /// for the opposite winding) the geometric normal is turned round to match, since the normals say which side is out.
+    pub fn with_shading_normal(mut self, normal: Vector3<f32>) -> HitRecord<'a> {
+        if normal.dot(&self.geometric_normal) < 0. {
+            self.geometric_normal = -self.geometric_normal;
+            self.front_face = !self.front_face;
+        }
+        self.normal = normal;
+        self
+    }
+
+    /// Where a ray leaving the hit in direction `dir` should start: nudged off the surface to the side it heads into,
+    /// so it doesn't hit the same surface again.
+    pub fn offset_origin(&self, dir: &Vector3<f32>, epsilon: f32) -> Vector3<f32> {
+        let n = self.geometric_normal;
+        if dir.dot(&n) < 0. { self.point - n * epsilon } else { self.point + n * epsilon }
+    }
+}
+
+/// Anything a ray can hit. New primitives only need to implement this to be rendered by `cast_ray`.
+/// Shapes are shared between render threads, hence `Send + Sync`.
+pub trait Shape: Send + Sync {
+    fn ray_intersect(&self, orig: &Vector3<f32>, dir: &Vector3<f32>) -> Option<HitRecord<'_>>;
+
+    /// Box enclosing the shape, used to place it in the scene's BVH. Shapes without one (infinite planes)
+    /// are tested against every ray.
+    fn bounding_box(&self) -> Option<Aabb> { None }
+}
+
+#[derive(Clone)]
+pub struct Sphere {
+    pub center: Vector3<f32>,
+    pub radius: f32,
+    pub material: Material
+}
+
+impl Sphere {
+    pub fn new(c: Vector3<f32>, r: f32, m: Material) -> Sphere {
+        Sphere {center: c, radius: r, material: m }
+    }
+
+    fn uv(N: &Vector3<f32>) -> Vector2<f32> { // spherical coordinates of the hit, u around the y axis and v from the bottom pole
+        let u = ((-N.z).atan2(N.x) + f32::consts::PI) / (2. * f32::consts::PI);
+        let v = (-N.y).clamp(-1., 1.).acos() / f32::consts::PI;
+        Vector2::new(u, v)
+    }
+}
+
+impl Shape for Sphere {
+    fn ray_intersect(&self, orig: &Vector3<f32>, dir: &Vector3<f32>) -> Option<HitRecord<'_>> {
+        let L = self.center - orig;
+        let tca: f32 = L.dot(dir);
+        let d2: f32 = L.dot(&L) - tca * tca;
+        if d2 > self.radius * self.radius { return None; }
+        let thc: f32 = (self.radius * self.radius - d2).sqrt();
+        let mut t0 = tca - thc;
+        let t1 = tca + thc;
+        if t0 < 0. { t0 = t1; }
+        if t0 < 0. { return None; }
+        let N = (orig + dir * t0 - self.center).normalize();
+        Some(HitRecord::new(orig, dir, t0, N, Sphere::uv(&N), &self.material))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vector3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(self.center - r, self.center + r))
+    }
+}
+
+/// A flat surface through `point`, either infinite or bounded to a rectangle centered on `point`.
+#[derive(Clone)]
+pub struct Plane {
+    pub point: Vector3<f32>,
+    pub normal: Vector3<f32>,
+    /// in-plane axes used for the rectangle bounds and the UV coordinates
+    pub u_axis: Vector3<f32>,
+    pub v_axis: Vector3<f32>,
+    /// half the rectangle's size along `u_axis` and `v_axis`, None for an infinite plane
+    pub half_extent: Option<Vector2<f32>>,
+    pub material: Material
+}
+
+impl Plane {
+    pub fn new(p: Vector3<f32>, n: Vector3<f32>, m: Material) -> Plane {
+        let n = n.normalize();
+        let reference = if n.z.abs() < 0.9 { Vector3::new(0., 0., 1.) } else { Vector3::new(1., 0., 0.) };
+        let u = n.cross(&reference).normalize();
+        Plane { point: p, normal: n, u_axis: u, v_axis: u.cross(&n), half_extent: None, material: m }
+    }
+
+    pub fn rectangle(center: Vector3<f32>, n: Vector3<f32>, width: f32, height: f32, m: Material) -> Plane {
+        Plane { half_extent: Some(Vector2::new(width / 2., height / 2.)), ..Plane::new(center, n, m) }
+    }
+
+    /// Turns the plane's in-plane axes so `u` lies along `u_dir` (projected onto the plane).
+    pub fn with_u_axis(mut self, u_dir: Vector3<f32>) -> Plane {
+        let u = (u_dir - self.normal * u_dir.dot(&self.normal)).normalize();
+        self.u_axis = u;
+        self.v_axis = u.cross(&self.normal);
+        self
+    }
+}
+
+impl Shape for Plane {
+    fn ray_intersect(&self, orig: &Vector3<f32>, dir: &Vector3<f32>) -> Option<HitRecord<'_>> {
+        let denom = dir.dot(&self.normal);
+        if denom.abs() < 1e-6 { return None; } // the ray runs parallel to the plane
+        let d = (self.point - orig).dot(&self.normal) / denom;
+        if d <= 0. { return None; }
+        let local = orig + dir * d - self.point;
+        let uv = Vector2::new(local.dot(&self.u_axis), local.dot(&self.v_axis));
+        if let Some(e) = self.half_extent {
+            if uv.x.abs() >= e.x || uv.y.abs() >= e.y { return None; }
+        }
+        Some(HitRecord::new(orig, dir, d, self.normal, uv, &self.material))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let e = self.half_extent?;
+        let (u, v) = (self.u_axis * e.x, self.v_axis * e.y);
+        Some(Aabb::from_points(&[self.point - u - v, self.point - u + v, self.point + u - v, self.point + u + v]))
+    }
+}
+
+/// Ray-triangle intersection (Möller-Trumbore). Returns the distance along the ray and the barycentric
+/// coordinates (u, v) of the hit, weighting `v1` and `v2`; `v0` gets `1 - u - v`.
+pub fn intersect_triangle(orig: &Vector3<f32>, dir: &Vector3<f32>, v0: &Vector3<f32>, v1: &Vector3<f32>, v2: &Vector3<f32>) -> Option<(f32, f32, f32)> {
+    let (e1, e2) = (v1 - v0, v2 - v0);
+    let p = dir.cross(&e2);
+    let det = e1.dot(&p);
+    if det.abs() < 1e-9 { return None; } // the ray runs parallel to the triangle, or it has no area
+    let inv_det = 1. / det;
+    let s = orig - v0;
+    let u = s.dot(&p) * inv_det;
+    if !(0. ..=1.).contains(&u) { return None; }
+    let q = s.cross(&e1);
+    let v = dir.dot(&q) * inv_det;
+    if v < 0. || u + v > 1. { return None; }
+    let t = e2.dot(&q) * inv_det;
+    if t <= 0. { return None; }
+    Some((t, u, v))
+}
+
+/// A single triangle. Its front is the side the vertices appear counter-clockwise from.
+#[derive(Clone)]
+pub struct Triangle {
+    pub vertices: [Vector3<f32>; 3],
+    /// texture coordinates at each vertex; without them the hit's barycentric coordinates are used
+    pub uvs: Option<[Vector2<f32>; 3]>,
+    pub material: Material
+}
+
+impl Triangle {
+    pub fn new(a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>, m: Material) -> Triangle {
+        Triangle { vertices: [a, b, c], uvs: None, material: m }
+    }
+
+    pub fn with_uvs(mut self, a: Vector2<f32>, b: Vector2<f32>, c: Vector2<f32>) -> Triangle {
+        self.uvs = Some([a, b, c]);
+        self
+    }
+
+    pub fn normal(&self) -> Vector3<f32> {
+        let [a, b, c] = self.vertices;
+        (b - a).cross(&(c - a)).normalize()
+    }
+}
+
+impl Shape for Triangle {
+    fn ray_intersect(&self, orig: &Vector3<f32>, dir: &Vector3<f32>) -> Option<HitRecord<'_>> {
+        let [a, b, c] = &self.vertices;
+        let (t, u, v) = intersect_triangle(orig, dir, a, b, c)?;
+        let uv = match self.uvs {
+            Some([ta, tb, tc]) => ta * (1. - u - v) + tb * u + tc * v,
+            None => Vector2::new(u, v)
+        };
+        Some(HitRecord::new(orig, dir, t, self.normal(), uv, &self.material))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> { Some(Aabb::from_points(&self.vertices)) }
+}
@@ -0,0 +1,66 @@
+use std::path::Path;
+use crate::Vector3;
+use crate::error::Result;
+use crate::formats::ppm::{self, PpmFormat};
+use crate::framebuffer::Framebuffer;
+use crate::objects::MaterialQuality;
+use crate::scene::Scene;
+use std::f32;
+
+pub fn reflect(I: &Vector3<f32>, N: &Vector3<f32>) -> Vector3<f32> {
+    I - N * 2. * (I.dot(N))
+}
+
+pub fn refract(I: &Vector3<f32>, N: &Vector3<f32>, eta_t: &f32, eta_i: &f32) -> Vector3<f32> { // Snell's law
+    let cosi: f32 = -I.dot(N).clamp(-1., 1.);
+    if cosi < 0. { // if the ray is inside the object, swap the indices and invert the normal to get the correct result
+        return refract(I, &-N, eta_i, eta_t);
+    }
+    let eta: f32 = eta_i / eta_t;
+    let k: f32 = 1. - eta * eta * (1. - cosi * cosi);
+    if k < 0. { Vector3::new(1.,0.,0.) } else { I * eta + N * (eta * cosi - k.sqrt()) }
+}
+
+/// Color seen along the ray. `depth` is the number of bounces the ray may still take, defaulting to the scene's `max_depth`.
+pub fn cast_ray(orig: &Vector3<f32>, dir: &Vector3<f32>, scene: &Scene, depth: Option<i32>) -> Vector3<f32> {
+    let depth = depth.unwrap_or(scene.max_depth as i32);
+    let mut diffuse_light = Vector3::new(0., 0., 0.);
+    let mut specular_light = Vector3::new(0., 0., 0.);
+
+    if depth < 0 { return scene.background_at(dir); }
+    let hit = match scene.intersect(orig, dir) {
+        Some(hit) => hit,
+        None => return scene.background_at(dir)
+    };
+    let (point, N, material) = (hit.point, hit.normal, hit.material);
+    let mut diffuse_color = material.diffuse_color_at(&hit.uv, &point);
+    if let Some(c) = hit.color { diffuse_color = diffuse_color.component_mul(&c); }
+
+    if !scene.is_lit() { return diffuse_color; }
+
+    for l in scene.lights.iter() {
+        let light_dir: Vector3<f32> = (l.position - point).normalize();
+        let light_distance: f32 = (l.position - point).norm();
+
+        let shadow_orig = hit.offset_origin(&light_dir, scene.epsilon); // checking if the point lies in the shadow of the lights[i]
+        if scene.occluded(&shadow_orig, &light_dir, light_distance) { continue; }
+
+        diffuse_light += l.color * l.intensity * 0f32.max(light_dir.dot(&N));
+        if material.has_quality(MaterialQuality::Smooth) {
+            specular_light += l.color * (0f32.max(-reflect(&-light_dir, &N).dot(dir)).powf(material.specular_exponent) * l.intensity);
+        }
+    }
+    let mut color = diffuse_color.component_mul(&diffuse_light);
+    if material.has_quality(MaterialQuality::Smooth) {
+        color = color * material.albedo[0] + specular_light.component_mul(&material.specular_at(&hit.uv, &point));
+    }
+    if material.has_quality(MaterialQuality::Reflective) {
+        color += material.surface_quality(dir, &hit, scene, depth);
+    }
+    color
+}
+
+/// Writes the image as a binary (P6) PPM; see `formats::ppm` for ASCII output and reading.
+pub fn save_ppm_image(path: &str, image: &Framebuffer) -> Result<()> {
+    ppm::save(Path::new(path), image, PpmFormat::Binary)
+}
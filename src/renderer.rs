@@ -0,0 +1,343 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::Vector3;
+use crate::camera::Camera;
+use crate::color::{luminance, srgb_to_linear};
+use crate::error;
+use crate::filter::{Filter, FilterSampler, PixelFilter};
+use crate::formats::{self, ImageFormat};
+use crate::framebuffer::Framebuffer;
+use crate::scene::Scene;
+use crate::render_funcs::cast_ray;
+use crate::sampling::{stratified, Rng, RunningStats};
+use crate::tiles::{tiles, BucketOrder};
+use crate::tonemap::{Exposure, ToneMapping};
+
+/// Rays a pixel takes before adaptive sampling judges its noise: the spread of fewer is too rough to trust,
+/// and with one there is no spread at all.
+const MIN_ADAPTIVE_SAMPLES: u32 = 8;
+
+#[derive(Clone, Debug)]
+pub struct RenderSettings {
+    pub width: usize,
+    pub height: usize,
+    /// rays per pixel; with more than one they are jittered within strata and spread out by the filter
+    pub samples: u32,
+    /// reconstruction filter weighting each sample by its distance from the pixel center
+    pub filter: PixelFilter,
+    /// turns on adaptive sampling: a pixel keeps getting batches of `samples` rays, at least 8 in all, until the
+    /// standard error of its luminance drops below this fraction of its mean, or `max_samples` is reached
+    pub noise_threshold: Option<f32>,
+    /// cap on rays per pixel with adaptive sampling
+    pub max_samples: u32,
+    /// where to write an image of how many rays each pixel took, if anywhere
+    pub sample_map: Option<String>,
+    /// overrides the scene's `max_depth` when set
+    pub max_depth: Option<u32>,
+    /// written format follows the extension: ppm, png, jpg/jpeg, bmp, tga, or hdr, pfm and exr for float output
+    pub output: String,
+    /// 1-100, only used for JPEG output
+    pub jpeg_quality: u8,
+    /// how radiance is squeezed into [0, 1] before the image is written as 8-bit
+    pub tone_mapping: ToneMapping,
+    /// exposure adjustment in stops, applied before tone mapping
+    pub exposure: f32,
+    /// worker threads to render with, 0 for one per core; the image comes out the same either way
+    pub threads: usize,
+    /// edge length of the square tiles (buckets) the image is rendered in
+    pub tile_size: usize,
+    pub bucket_order: BucketOrder
+}
+
+impl RenderSettings {
+    pub fn new(width: usize, height: usize, output: &str) -> RenderSettings {
+        RenderSettings { width, height, samples: 1, filter: PixelFilter::Box, noise_threshold: None, max_samples: 64, sample_map: None, max_depth: None, output: output.to_string(), jpeg_quality: 90, tone_mapping: ToneMapping::Clamp, exposure: 0., threads: 0, tile_size: 32, bucket_order: BucketOrder::Spiral }
+    }
+
+    /// 800x600 settings with anything given on the command line applied on top:
+    /// `--width N`, `--height N`, `--samples N`, `--filter box|tent|gaussian|mitchell`,
+    /// `--noise-threshold X`, `--max-samples N`, `--sample-map PATH`, `--max-depth N`, `--output PATH` (or `-o PATH`), `--quality N`,
+    /// `--tonemap clamp|max|reinhard|aces`, `--exposure EV`, `--threads N`, `--tile-size N` and `--buckets scanline|spiral|hilbert`.
+    pub fn from_args(default_output: &str) -> Result<RenderSettings, String> {
+        let mut settings = RenderSettings::new(800, 600, default_output);
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let value = args.next().ok_or_else(|| format!("missing value for {}", arg))?;
+            let number = || value.parse::<u32>().map_err(|_| format!("{} expects a number, got {}", arg, value));
+            match arg.as_str() {
+                "--width" => settings.width = number()? as usize,
+                "--height" => settings.height = number()? as usize,
+                "--samples" => settings.samples = number()?,
+                "--filter" => settings.filter = value.parse()?,
+                "--noise-threshold" => settings.noise_threshold = Some(value.parse().map_err(|_| format!("{} expects a number, got {}", arg, value))?),
+                "--max-samples" => settings.max_samples = number()?.max(1),
+                "--sample-map" => settings.sample_map = Some(value.clone()),
+                "--max-depth" => settings.max_depth = Some(number()?),
+                "--output" | "-o" => settings.output = value.clone(),
+                "--quality" => settings.jpeg_quality = number()?.clamp(1, 100) as u8,
+                "--tonemap" => settings.tone_mapping = value.parse()?,
+                "--threads" => settings.threads = number()? as usize,
+                "--tile-size" => settings.tile_size = number()?.max(1) as usize,
+                "--buckets" => settings.bucket_order = value.parse()?,
+                "--exposure" => settings.exposure = value.parse().map_err(|_| format!("{} expects a number, got {}", arg, value))?,
+                _ => return Err(format!("unknown option {}", arg))
+            }
+        }
+        Ok(settings)
+    }
+
+    pub fn aspect_ratio(&self) -> f32 { self.width as f32 / self.height as f32 }
+
+    /// Number of threads actually used, resolving 0 to the number of cores.
+    pub fn thread_count(&self) -> usize {
+        if self.threads > 0 { return self.threads; }
+        thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> RenderSettings { RenderSettings::new(800, 600, "out.ppm") }
+}
+
+/// How far a render has got, passed to the progress callback after every finished tile.
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    pub tiles_done: usize,
+    pub tiles_total: usize,
+    pub elapsed: Duration,
+    /// extrapolated from the average time per tile so far
+    pub eta: Duration
+}
+
+impl Progress {
+    pub fn fraction(&self) -> f32 { self.tiles_done as f32 / self.tiles_total.max(1) as f32 }
+}
+
+/// Writes a one-line progress report to stderr, overwriting the previous one.
+pub fn print_progress(p: Progress) {
+    eprint!("\r{:>5.1}% ({}/{} tiles), {:.1}s elapsed, {:.1}s left ", p.fraction() * 100., p.tiles_done, p.tiles_total,
+        p.elapsed.as_secs_f32(), p.eta.as_secs_f32());
+    if p.tiles_done == p.tiles_total { eprintln!(); }
+}
+
+/// Shared flag for stopping a render from another thread. Tiles already started are finished,
+/// the rest are skipped and stay black in the returned image.
+#[derive(Clone, Default, Debug)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken { CancelToken::default() }
+
+    pub fn cancel(&self) { self.0.store(true, Ordering::SeqCst); }
+
+    pub fn is_cancelled(&self) -> bool { self.0.load(Ordering::SeqCst) }
+
+    /// Clears the flag so the renderer can be used again.
+    pub fn reset(&self) { self.0.store(false, Ordering::SeqCst); }
+}
+
+pub struct Renderer {
+    pub settings: RenderSettings,
+    progress: Option<Box<dyn Fn(Progress) + Send + Sync>>,
+    cancel: CancelToken
+}
+
+impl Renderer {
+    pub fn new(settings: RenderSettings) -> Renderer { Renderer { settings, progress: None, cancel: CancelToken::new() } }
+
+    /// Calls `f` every time a tile finishes. It is called from the worker threads, one call at a time.
+    pub fn with_progress<F: Fn(Progress) + Send + Sync + 'static>(mut self, f: F) -> Renderer {
+        self.progress = Some(Box::new(f));
+        self
+    }
+
+    /// Token that stops the current (or next) render when cancelled; keep a clone to call `cancel` on.
+    pub fn cancel_token(&self) -> CancelToken { self.cancel.clone() }
+
+    /// Traces the scene through the camera into a new image of the configured size.
+    /// If the render is cancelled the image only holds the tiles finished so far.
+    pub fn render(&self, scene: &Scene, camera: &Camera) -> Framebuffer { self.render_with_sample_map(scene, camera).0 }
+
+    /// Like `render`, also returning an image holding the number of rays traced for each pixel.
+    pub fn render_with_sample_map(&self, scene: &Scene, camera: &Camera) -> (Framebuffer, Framebuffer) {
+        let (width, height) = (self.settings.width, self.settings.height);
+        let depth = self.settings.max_depth.map(|d| d as i32);
+        let batch = self.settings.samples.max(1);
+        let max_samples = match self.settings.noise_threshold {
+            Some(_) => self.settings.max_samples.max(batch),
+            None => batch
+        };
+        let min_samples = MIN_ADAPTIVE_SAMPLES.min(max_samples);
+        let filter = self.settings.filter;
+        let sampler = FilterSampler::new(&filter);
+        let counts: Vec<AtomicU32> = (0..width * height).map(|_| AtomicU32::new(0)).collect();
+
+        let image = self.render_pixels(|i, j| {
+            let trace = |dx: f32, dy: f32| {
+                let (orig, dir) = camera.primary_ray(i as f32 + 0.5 + dx, j as f32 + 0.5 + dy, width, height);
+                cast_ray(&orig, &dir, scene, depth)
+            };
+            if max_samples == 1 {
+                counts[j * width + i].store(1, Ordering::Relaxed);
+                return trace(0., 0.);
+            }
+
+            let mut rng = Rng::for_pixel(i, j);
+            let mut stats = RunningStats::default();
+            let (mut weighted, mut plain) = (Vector3::new(0., 0., 0.), Vector3::new(0., 0., 0.));
+            let mut total = 0.;
+            while stats.count < max_samples {
+                let n = batch.min(max_samples - stats.count); // each batch is stratified on its own
+                for k in 0..n {
+                    let (u, v) = stratified(k, n, &mut rng);
+                    let ((dx, pdf_x), (dy, pdf_y)) = (sampler.sample(u), sampler.sample(v));
+                    let weight = filter.weight(dx, dy) / (pdf_x * pdf_y);
+                    let c = trace(dx, dy);
+                    weighted += c * weight;
+                    plain += c;
+                    total += weight;
+                    stats.add(luminance(&c));
+                }
+                match self.settings.noise_threshold {
+                    // the floor keeps near-black pixels from chasing noise nobody can see
+                    Some(_) if stats.count < min_samples => continue,
+                    Some(threshold) if stats.std_error() > threshold * stats.mean.max(0.01) => continue,
+                    _ => break
+                }
+            }
+            counts[j * width + i].store(stats.count, Ordering::Relaxed);
+            // filters with negative lobes can, rarely, leave nothing to normalize by
+            if total > 0. { weighted / total } else { plain / stats.count as f32 }
+        });
+
+        let map = counts.iter().map(|n| { let n = n.load(Ordering::Relaxed) as f32; Vector3::new(n, n, n) }).collect();
+        (image, Framebuffer::from_pixels(width, height, map).unwrap())
+    }
+
+    /// Fills an image of the configured size with `pixel(i, j)` for every column `i` and row `j`.
+    /// Tiles are handed out to the worker threads in bucket order as they free up.
+    pub fn render_pixels<F: Fn(usize, usize) -> Vector3<f32> + Sync>(&self, pixel: F) -> Framebuffer {
+        let (width, height) = (self.settings.width, self.settings.height);
+        let tiles = tiles(width, height, self.settings.tile_size, self.settings.bucket_order);
+        let next_tile = AtomicUsize::new(0);
+        let finished = Mutex::new((Framebuffer::new(width, height), 0));
+        let start = Instant::now();
+
+        thread::scope(|s| {
+            for _ in 0..self.settings.thread_count() {
+                s.spawn(|| loop {
+                    if self.cancel.is_cancelled() { break; }
+                    let tile = match tiles.get(next_tile.fetch_add(1, Ordering::SeqCst)) {
+                        Some(t) => *t,
+                        None => break
+                    };
+                    let mut block = Vec::with_capacity(tile.width * tile.height);
+                    for j in tile.y..tile.y + tile.height {
+                        for i in tile.x..tile.x + tile.width {
+                            block.push(pixel(i, j));
+                        }
+                    }
+
+                    let mut finished = finished.lock().unwrap();
+                    let (image, done) = &mut *finished;
+                    for (n, p) in block.into_iter().enumerate() {
+                        image.set(tile.x + n % tile.width, tile.y + n / tile.width, p);
+                    }
+                    *done += 1;
+                    if let Some(ref progress) = self.progress {
+                        let elapsed = start.elapsed();
+                        let eta = elapsed.mul_f64((tiles.len() - *done) as f64 / *done as f64);
+                        progress(Progress { tiles_done: *done, tiles_total: tiles.len(), elapsed, eta });
+                    }
+                });
+            }
+        });
+        finished.into_inner().unwrap().0
+    }
+
+    /// Tone maps the image with the configured settings and writes it to the output path.
+    pub fn save(&self, image: &Framebuffer) -> error::Result<()> {
+        let format = match ImageFormat::from_path(Path::new(&self.settings.output))? {
+            ImageFormat::Jpeg(_) => ImageFormat::Jpeg(self.settings.jpeg_quality),
+            format => format
+        };
+        let mapped = if format.is_hdr() {
+            image.map(|c| c * 2f32.powf(self.settings.exposure)) // float formats keep the full range, so only exposure applies
+        } else {
+            image.tone_mapped(&Exposure::new(self.settings.exposure, self.settings.tone_mapping))
+        };
+        formats::save_as(&self.settings.output, &mapped, format)
+    }
+
+    /// Writes a sample map from `render_with_sample_map` to the configured `sample_map` path, if there is one.
+    /// Float formats get the raw ray counts; others are scaled so white is the most rays a pixel can take.
+    pub fn save_sample_map(&self, map: &Framebuffer) -> error::Result<()> {
+        let path = match self.settings.sample_map {
+            Some(ref path) => path,
+            None => return Ok(())
+        };
+        let format = ImageFormat::from_path(Path::new(path))?;
+        let max_samples = match self.settings.noise_threshold {
+            Some(_) => self.settings.max_samples.max(self.settings.samples),
+            None => self.settings.samples
+        }.max(1) as f32;
+        let scaled = if format.is_hdr() { map.clone() } else { map.map(|c| c.map(|n| srgb_to_linear(n / max_samples))) };
+        formats::save_as(path, &scaled, format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Vector3, Vector4};
+    use crate::color::srgb;
+    use crate::objects::{Light, Material, MaterialQuality, Sphere};
+
+    fn glass_sphere_scene() -> Scene {
+        let glass = Material::new(srgb(0.6, 0.7, 0.8), Some(Vector4::new(0., 0.5, 0.1, 0.8)), Some(1.5), Some(125.),
+            Some(vec![MaterialQuality::Smooth, MaterialQuality::Reflective, MaterialQuality::Refractive]));
+        Scene::builder()
+            .shape(Sphere::new(Vector3::new(0., 0., -5.), 1.5, glass))
+            .light(Light::new(&Vector3::new(-5., 5., 5.), &1.5))
+            .build()
+    }
+
+    #[test]
+    fn adaptive_sampling_runs_with_one_sample_per_batch() {
+        let scene = glass_sphere_scene();
+        let mut settings = RenderSettings::new(80, 60, "unused.ppm");
+        settings.noise_threshold = Some(0.01);
+        settings.max_samples = 64;
+        let (_, map) = Renderer::new(settings).render_with_sample_map(&scene, &Camera::with_fov(60., 80. / 60.));
+
+        let counts: Vec<f32> = map.pixels().iter().map(|p| p.x).collect();
+        assert!(counts.iter().all(|&n| n >= MIN_ADAPTIVE_SAMPLES as f32 && n <= 64.));
+        assert!(counts.iter().any(|&n| n > MIN_ADAPTIVE_SAMPLES as f32), "no pixel took more than the minimum");
+    }
+
+    #[test]
+    fn output_is_the_same_with_any_thread_count() {
+        let scene = glass_sphere_scene();
+        let camera = Camera::with_fov(60., 70. / 50.);
+        for &order in &[BucketOrder::Scanline, BucketOrder::Spiral, BucketOrder::Hilbert] {
+            let render = |threads: usize| {
+                let mut settings = RenderSettings::new(70, 50, "unused.ppm");
+                settings.samples = 2;
+                settings.filter = PixelFilter::Mitchell;
+                settings.noise_threshold = Some(0.05);
+                settings.max_samples = 16;
+                settings.threads = threads;
+                settings.tile_size = 16; // doesn't divide either side, so the last tiles are partial
+                settings.bucket_order = order;
+                Renderer::new(settings).render_with_sample_map(&scene, &camera)
+            };
+            let single = render(1);
+            for &threads in &[2, 7] {
+                assert_eq!(render(threads), single, "{} threads, {:?} buckets", threads, order);
+            }
+        }
+    }
+}
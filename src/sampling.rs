@@ -0,0 +1,65 @@
+/// Small PCG32 random number generator. Seeding it from the pixel coordinates keeps every pixel's
+/// samples the same no matter which thread renders it, so renders are reproducible.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+    inc: u64
+}
+
+impl Rng {
+    pub fn new(seed: u64, stream: u64) -> Rng {
+        let mut rng = Rng { state: 0, inc: (stream << 1) | 1 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    /// Generator for the samples of pixel (i, j).
+    pub fn for_pixel(i: usize, j: usize) -> Rng {
+        Rng::new((j as u64) << 32 | i as u64, 0x5851_f42d_4c95_7f2d)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(self.inc);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    /// Uniform float in [0, 1).
+    pub fn next_f32(&mut self) -> f32 { (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32 }
+}
+
+/// The `k`th of `n` points spread over the unit square: the square is cut into a grid of
+/// (nearly) `n` cells and each point lands at a random spot inside its own cell.
+pub fn stratified(k: u32, n: u32, rng: &mut Rng) -> (f32, f32) {
+    let cols = (n as f32).sqrt().ceil().max(1.) as u32;
+    let rows = n.div_ceil(cols);
+    let (cx, cy) = (k % cols, k / cols);
+    ((cx as f32 + rng.next_f32()) / cols as f32, (cy as f32 + rng.next_f32()) / rows as f32)
+}
+
+/// Running mean and variance of a stream of values (Welford's method), without keeping the values.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct RunningStats {
+    pub count: u32,
+    pub mean: f32,
+    m2: f32
+}
+
+impl RunningStats {
+    pub fn add(&mut self, x: f32) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f32;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    /// Sample variance, 0 until there are two values.
+    pub fn variance(&self) -> f32 { if self.count < 2 { 0. } else { self.m2 / (self.count - 1) as f32 } }
+
+    /// Standard error of the mean: how far the mean is likely to be off.
+    pub fn std_error(&self) -> f32 { (self.variance() / self.count.max(1) as f32).sqrt() }
+}
@@ -0,0 +1,257 @@
+use std::sync::OnceLock;
+use crate::Vector3;
+use crate::bvh::Bvh;
+use crate::color::srgb;
+use crate::environment::EnvironmentMap;
+use crate::objects::{Shape, HitRecord, Light};
+
+/// Hits further away than this are ignored.
+const MAX_DISTANCE: f32 = 1000.;
+
+/// How `Scene::intersect` finds the closest shape.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Accelerator {
+    /// bounding volume hierarchy over the shapes' boxes
+    Bvh,
+    /// test every shape, for checking the BVH against
+    BruteForce
+}
+
+/// The BVH over the shapes that have bounds, plus the ones that don't.
+struct SceneBvh {
+    bvh: Bvh,
+    unbounded: Vec<usize>
+}
+
+/// Everything a ray can interact with, plus the settings that control how rays are traced through it.
+pub struct Scene {
+    /// private so every change goes through a method that drops the BVH built over them
+    shapes: Vec<Box<dyn Shape>>,
+    /// with no lights the shapes are drawn flat in their diffuse color
+    pub lights: Vec<Light>,
+    /// linear color returned by rays that miss everything
+    pub background: Vector3<f32>,
+    /// takes the place of `background` when set
+    pub environment: Option<EnvironmentMap>,
+    /// how many reflection/refraction bounces a ray may take before it returns the background
+    pub max_depth: u32,
+    /// offset applied along the normal when spawning secondary rays, so they don't hit the surface they start on
+    pub epsilon: f32,
+    pub accelerator: Accelerator,
+    bvh: OnceLock<SceneBvh>
+}
+
+impl Scene {
+    pub fn new() -> Scene {
+        Scene { shapes: vec![], lights: vec![], background: srgb(0.2, 0.7, 0.8), environment: None, max_depth: 4, epsilon: 1e-3, accelerator: Accelerator::Bvh, bvh: OnceLock::new() }
+    }
+
+    pub fn builder() -> SceneBuilder { SceneBuilder { scene: Scene::new() } }
+
+    pub fn is_lit(&self) -> bool { !self.lights.is_empty() }
+
+    pub fn shapes(&self) -> &[Box<dyn Shape>] { &self.shapes }
+
+    pub fn add_shape<S: Shape + 'static>(&mut self, shape: S) {
+        self.shapes_mut().push(Box::new(shape));
+    }
+
+    /// The shapes for adding, removing or changing; the BVH is rebuilt from them on the next intersection test.
+    pub fn shapes_mut(&mut self) -> &mut Vec<Box<dyn Shape>> {
+        self.bvh = OnceLock::new();
+        &mut self.shapes
+    }
+
+    /// What a ray heading in direction `dir` sees when it misses everything.
+    pub fn background_at(&self, dir: &Vector3<f32>) -> Vector3<f32> {
+        match self.environment {
+            Some(ref env) => env.color(dir),
+            None => self.background
+        }
+    }
+
+    /// Closest hit among the scene's shapes along the ray, if any lies within the render distance.
+    /// Where shapes are hit at exactly the same distance the one added first wins, whichever accelerator is used.
+    pub fn intersect(&self, orig: &Vector3<f32>, dir: &Vector3<f32>) -> Option<HitRecord<'_>> {
+        let hit = |i: usize| self.shapes[i].ray_intersect(orig, dir).map(|h| (i, h));
+        let mut closest = None;
+        match self.accelerator {
+            Accelerator::BruteForce => for i in 0..self.shapes.len() { keep_nearer(&mut closest, hit(i)); },
+            Accelerator::Bvh => {
+                let accel = self.bvh();
+                closest = accel.bvh.closest(orig, dir, MAX_DISTANCE, |i| hit(i).map(|h| (h.1.distance, h)));
+                for &i in &accel.unbounded { keep_nearer(&mut closest, hit(i)); }
+            }
+        }
+        closest.map(|c| c.1)
+    }
+
+    /// Whether anything lies along the ray closer than `max_distance`, as for a shadow ray towards a light.
+    pub fn occluded(&self, orig: &Vector3<f32>, dir: &Vector3<f32>, max_distance: f32) -> bool {
+        let max_distance = max_distance.min(MAX_DISTANCE);
+        let blocks = |i: usize| self.shapes[i].ray_intersect(orig, dir).is_some_and(|h| h.distance < max_distance);
+        match self.accelerator {
+            Accelerator::BruteForce => (0..self.shapes.len()).any(blocks),
+            Accelerator::Bvh => {
+                let accel = self.bvh();
+                accel.unbounded.iter().any(|&i| blocks(i))
+                    || accel.bvh.any(orig, dir, max_distance, |i| self.shapes[i].ray_intersect(orig, dir).map(|h| h.distance))
+            }
+        }
+    }
+
+    fn bvh(&self) -> &SceneBvh {
+        self.bvh.get_or_init(|| {
+            let (mut bounded, mut unbounded) = (vec![], vec![]);
+            for (i, s) in self.shapes.iter().enumerate() {
+                match s.bounding_box() {
+                    Some(b) => bounded.push((i, b)),
+                    None => unbounded.push(i)
+                }
+            }
+            SceneBvh { bvh: Bvh::new(bounded), unbounded }
+        })
+    }
+}
+
+/// Replaces `best` with `candidate` (a shape index and its hit) if it is nearer, or as near and added earlier.
+fn keep_nearer<'a>(best: &mut Option<(usize, HitRecord<'a>)>, candidate: Option<(usize, HitRecord<'a>)>) {
+    let c = match candidate {
+        Some(c) => c,
+        None => return
+    };
+    let nearer = match best {
+        Some((i, b)) => c.1.distance < b.distance || (c.1.distance == b.distance && c.0 < *i),
+        None => c.1.distance < MAX_DISTANCE
+    };
+    if nearer { *best = Some(c); }
+}
+
+impl Default for Scene {
+    fn default() -> Scene { Scene::new() }
+}
+
+pub struct SceneBuilder {
+    scene: Scene
+}
+
+impl SceneBuilder {
+    pub fn shape<S: Shape + 'static>(mut self, shape: S) -> SceneBuilder {
+        self.scene.shapes.push(Box::new(shape));
+        self
+    }
+
+    pub fn shapes(mut self, shapes: Vec<Box<dyn Shape>>) -> SceneBuilder {
+        self.scene.shapes.extend(shapes);
+        self
+    }
+
+    pub fn light(mut self, light: Light) -> SceneBuilder {
+        self.scene.lights.push(light);
+        self
+    }
+
+    pub fn lights(mut self, lights: Vec<Light>) -> SceneBuilder {
+        self.scene.lights.extend(lights);
+        self
+    }
+
+    pub fn background(mut self, color: Vector3<f32>) -> SceneBuilder {
+        self.scene.background = color;
+        self
+    }
+
+    pub fn environment(mut self, environment: EnvironmentMap) -> SceneBuilder {
+        self.scene.environment = Some(environment);
+        self
+    }
+
+    pub fn max_depth(mut self, depth: u32) -> SceneBuilder {
+        self.scene.max_depth = depth;
+        self
+    }
+
+    pub fn epsilon(mut self, epsilon: f32) -> SceneBuilder {
+        self.scene.epsilon = epsilon;
+        self
+    }
+
+    pub fn accelerator(mut self, accelerator: Accelerator) -> SceneBuilder {
+        self.scene.accelerator = accelerator;
+        self
+    }
+
+    pub fn build(self) -> Scene { self.scene }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{Material, Plane, Sphere, Triangle};
+    use crate::sampling::Rng;
+
+    fn random_point(rng: &mut Rng, extent: f32) -> Vector3<f32> {
+        Vector3::new(rng.next_f32() - 0.5, rng.next_f32() - 0.5, rng.next_f32() - 0.5) * 2. * extent
+    }
+
+    /// `triangles` random triangles and `spheres` random spheres in a 20 unit cube, plus `planes` planes through it.
+    fn random_scene(seed: u64, triangles: usize, spheres: usize, planes: usize, accelerator: Accelerator) -> Scene {
+        let mut rng = Rng::new(seed, 1);
+        let m = Material::new(srgb(0.5, 0.5, 0.5), None, None, None, None);
+        let mut builder = Scene::builder().accelerator(accelerator);
+        for _ in 0..triangles {
+            let a = random_point(&mut rng, 10.);
+            let (b, c) = (a + random_point(&mut rng, 2.), a + random_point(&mut rng, 2.));
+            builder = builder.shape(Triangle::new(a, b, c, m.clone()));
+        }
+        for _ in 0..spheres {
+            builder = builder.shape(Sphere::new(random_point(&mut rng, 10.), 0.2 + rng.next_f32(), m.clone()));
+        }
+        for i in 0..planes {
+            let (p, n) = (random_point(&mut rng, 10.), random_point(&mut rng, 1.));
+            builder = builder.shape(if i % 2 == 0 { Plane::new(p, n, m.clone()) } else { Plane::rectangle(p, n, 4., 3., m.clone()) });
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn bvh_matches_brute_force() {
+        for (seed, &(triangles, spheres, planes)) in [(200, 0, 0), (0, 100, 0), (150, 50, 4), (3, 2, 1)].iter().enumerate() {
+            let bvh = random_scene(seed as u64, triangles, spheres, planes, Accelerator::Bvh);
+            let brute = random_scene(seed as u64, triangles, spheres, planes, Accelerator::BruteForce);
+            let mut rng = Rng::new(seed as u64, 2);
+            let mut hits = 0;
+            for _ in 0..5000 {
+                let orig = random_point(&mut rng, 15.);
+                let dir = random_point(&mut rng, 1.).normalize();
+                let (a, b) = (bvh.intersect(&orig, &dir), brute.intersect(&orig, &dir));
+                match (&a, &b) {
+                    (Some(a), Some(b)) => {
+                        assert_eq!((a.distance, a.point, a.normal), (b.distance, b.point, b.normal), "ray from {:?} along {:?}", orig, dir);
+                        hits += 1;
+                    }
+                    (None, None) => {}
+                    _ => panic!("only one accelerator hit the ray from {:?} along {:?}", orig, dir)
+                }
+                let max_distance = rng.next_f32() * 30.;
+                assert_eq!(bvh.occluded(&orig, &dir, max_distance), brute.occluded(&orig, &dir, max_distance));
+            }
+            assert!(hits > 0, "scene {} was never hit", seed);
+        }
+    }
+
+    #[test]
+    fn changing_shapes_rebuilds_the_bvh() {
+        let m = Material::new(srgb(0.5, 0.5, 0.5), None, None, None, None);
+        let (orig, dir) = (Vector3::new(0., 0., 0.), Vector3::new(0., 0., -1.));
+        let mut scene = Scene::builder().shape(Sphere::new(Vector3::new(0., 0., -10.), 1., m.clone())).build();
+        assert_eq!(scene.intersect(&orig, &dir).map(|h| h.distance), Some(9.));
+
+        scene.add_shape(Sphere::new(Vector3::new(0., 0., -5.), 1., m));
+        assert_eq!(scene.intersect(&orig, &dir).map(|h| h.distance), Some(4.));
+
+        scene.shapes_mut().clear();
+        assert!(scene.intersect(&orig, &dir).is_none());
+        assert!(!scene.occluded(&orig, &dir, 100.));
+    }
+}
@@ -0,0 +1,309 @@
+use std::path::Path;
+use std::sync::Arc;
+use crate::{Vector2, Vector3};
+use crate::error::Result;
+use crate::formats;
+use crate::framebuffer::Framebuffer;
+use crate::noise::Noise;
+
+/// A color that varies over a surface, looked up by the hit's UV coordinates and/or its position in the scene.
+pub trait Texture: Send + Sync {
+    fn value(&self, uv: &Vector2<f32>, point: &Vector3<f32>) -> Vector3<f32>;
+}
+
+/// The same color everywhere, for filling in where a texture is expected.
+#[derive(Clone, Copy)]
+pub struct Constant(pub Vector3<f32>);
+
+impl Texture for Constant {
+    fn value(&self, _uv: &Vector2<f32>, _point: &Vector3<f32>) -> Vector3<f32> { self.0 }
+}
+
+/// Alternating squares laid out over the surface's UV coordinates, each filled in by one of two textures.
+/// The textures are looked up at the hit as usual, so a pattern runs on unbroken across the squares showing it.
+#[derive(Clone)]
+pub struct Checker {
+    pub even: Arc<dyn Texture>,
+    pub odd: Arc<dyn Texture>,
+    /// squares per unit of UV
+    pub scale: f32
+}
+
+impl Checker {
+    /// Squares of two plain colors.
+    pub fn new(even: Vector3<f32>, odd: Vector3<f32>, scale: f32) -> Checker {
+        Checker::textured(Arc::new(Constant(even)), Arc::new(Constant(odd)), scale)
+    }
+
+    pub fn textured(even: Arc<dyn Texture>, odd: Arc<dyn Texture>, scale: f32) -> Checker {
+        Checker { even, odd, scale }
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, uv: &Vector2<f32>, point: &Vector3<f32>) -> Vector3<f32> {
+        let square = (uv.x * self.scale).floor() as i64 + (uv.y * self.scale).floor() as i64;
+        if square & 1 == 0 { self.even.value(uv, point) } else { self.odd.value(uv, point) }
+    }
+}
+
+/// How the layers of a `NoiseTexture` are summed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Fractal {
+    /// soft, cloudy blotches (`Noise::fbm`)
+    Fbm,
+    /// billowy patches with sharp creases between them (`Noise::turbulence`)
+    Turbulence
+}
+
+/// Blends between two colors by noise over the hit's position in the scene, so the pattern runs through
+/// objects like a solid material rather than being wrapped onto their surface.
+#[derive(Clone)]
+pub struct NoiseTexture {
+    pub noise: Noise,
+    pub fractal: Fractal,
+    pub low: Vector3<f32>,
+    pub high: Vector3<f32>,
+    /// features per unit of distance
+    pub scale: f32,
+    pub octaves: u32
+}
+
+impl NoiseTexture {
+    /// Five octaves of fBm at one feature per unit, from the default seed.
+    pub fn new(low: Vector3<f32>, high: Vector3<f32>) -> NoiseTexture {
+        NoiseTexture { noise: Noise::default(), fractal: Fractal::Fbm, low, high, scale: 1., octaves: 5 }
+    }
+
+    pub fn with_noise(mut self, noise: Noise) -> NoiseTexture {
+        self.noise = noise;
+        self
+    }
+
+    pub fn with_fractal(mut self, fractal: Fractal) -> NoiseTexture {
+        self.fractal = fractal;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> NoiseTexture {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_octaves(mut self, octaves: u32) -> NoiseTexture {
+        self.octaves = octaves;
+        self
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _uv: &Vector2<f32>, point: &Vector3<f32>) -> Vector3<f32> {
+        let p = point * self.scale;
+        let t = match self.fractal {
+            Fractal::Fbm => 0.5 + 0.5 * self.noise.fbm(&p, self.octaves),
+            Fractal::Turbulence => self.noise.turbulence(&p, self.octaves)
+        };
+        mix(&self.low, &self.high, t)
+    }
+}
+
+/// Veins running across a base color: stripes along the x axis, bent and broken up by turbulence.
+#[derive(Clone)]
+pub struct Marble {
+    pub noise: Noise,
+    pub base: Vector3<f32>,
+    pub vein: Vector3<f32>,
+    /// stripes per unit of distance
+    pub scale: f32,
+    /// how far the turbulence pushes the stripes about, with 0 giving straight bands
+    pub distortion: f32,
+    pub octaves: u32
+}
+
+impl Marble {
+    pub fn new(base: Vector3<f32>, vein: Vector3<f32>) -> Marble {
+        Marble { noise: Noise::default(), base, vein, scale: 1., distortion: 5., octaves: 6 }
+    }
+
+    pub fn with_noise(mut self, noise: Noise) -> Marble {
+        self.noise = noise;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Marble {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_distortion(mut self, distortion: f32) -> Marble {
+        self.distortion = distortion;
+        self
+    }
+}
+
+impl Texture for Marble {
+    fn value(&self, _uv: &Vector2<f32>, point: &Vector3<f32>) -> Vector3<f32> {
+        let p = point * self.scale;
+        let phase = p.x * std::f32::consts::PI + self.distortion * self.noise.turbulence(&p, self.octaves);
+        // thin dark veins where the sine crosses zero, broad base color in between
+        mix(&self.vein, &self.base, phase.sin().abs().sqrt())
+    }
+}
+
+/// Growth rings around the y axis, wobbled by noise so they aren't perfect circles.
+#[derive(Clone)]
+pub struct Wood {
+    pub noise: Noise,
+    pub light: Vector3<f32>,
+    pub dark: Vector3<f32>,
+    /// rings per unit of distance from the axis
+    pub rings: f32,
+    /// how far the noise pushes the rings about, in rings
+    pub distortion: f32,
+    pub octaves: u32
+}
+
+impl Wood {
+    pub fn new(light: Vector3<f32>, dark: Vector3<f32>) -> Wood {
+        Wood { noise: Noise::default(), light, dark, rings: 4., distortion: 0.5, octaves: 3 }
+    }
+
+    pub fn with_noise(mut self, noise: Noise) -> Wood {
+        self.noise = noise;
+        self
+    }
+
+    pub fn with_rings(mut self, rings: f32) -> Wood {
+        self.rings = rings;
+        self
+    }
+
+    pub fn with_distortion(mut self, distortion: f32) -> Wood {
+        self.distortion = distortion;
+        self
+    }
+}
+
+impl Texture for Wood {
+    fn value(&self, _uv: &Vector2<f32>, point: &Vector3<f32>) -> Vector3<f32> {
+        let radius = (point.x * point.x + point.z * point.z).sqrt() * self.rings;
+        let ring = radius + self.distortion * self.noise.fbm(&(point * self.rings), self.octaves);
+        // light early wood shading into a narrow band of dark late wood at the end of each ring
+        mix(&self.light, &self.dark, ring.rem_euclid(1.).powi(3))
+    }
+}
+
+fn mix(a: &Vector3<f32>, b: &Vector3<f32>, t: f32) -> Vector3<f32> {
+    let t = t.clamp(0., 1.);
+    a * (1. - t) + b * t
+}
+
+/// How an image texture continues past the edges of the [0, 1] UV square.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Wrap {
+    /// tiles the image
+    Repeat,
+    /// stretches the edge pixels outwards
+    Clamp,
+    /// tiles the image, flipping every other copy so the seams match up
+    Mirror
+}
+
+impl Wrap {
+    /// Brings a pixel index that may lie outside the image back into `0..n`.
+    fn index(self, i: i64, n: usize) -> usize {
+        let n = n as i64;
+        let i = match self {
+            Wrap::Repeat => i.rem_euclid(n),
+            Wrap::Clamp => i.clamp(0, n - 1),
+            Wrap::Mirror => {
+                let i = i.rem_euclid(2 * n);
+                if i < n { i } else { 2 * n - 1 - i }
+            }
+        };
+        i as usize
+    }
+}
+
+/// How an image texture is looked up between pixel centers.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Interpolation {
+    /// the pixel the point falls in, giving hard-edged texels up close
+    Nearest,
+    /// blends the four nearest pixels
+    Bilinear
+}
+
+/// An image stretched over the surface's UV coordinates, with (0, 0) at the image's bottom left and (1, 1) at its
+/// top right: spheres wrap it around themselves once and planes repeat it every unit of their axes.
+#[derive(Clone)]
+pub struct ImageTexture {
+    /// linear colors, shared so one file can texture many materials
+    pub image: Arc<Framebuffer>,
+    pub wrap: Wrap,
+    pub interpolation: Interpolation,
+    /// copies of the image per unit of UV along each axis
+    pub scale: Vector2<f32>,
+    /// multiplies every pixel, for a material that gives both a color and a map
+    pub tint: Vector3<f32>
+}
+
+impl ImageTexture {
+    /// Repeating, bilinear filtered and unscaled.
+    pub fn new(image: Arc<Framebuffer>) -> ImageTexture {
+        ImageTexture { image, wrap: Wrap::Repeat, interpolation: Interpolation::Bilinear, scale: Vector2::new(1., 1.), tint: Vector3::new(1., 1., 1.) }
+    }
+
+    /// Reads the image with `formats::load`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ImageTexture> {
+        Ok(ImageTexture::new(Arc::new(formats::load(path)?)))
+    }
+
+    pub fn with_wrap(mut self, wrap: Wrap) -> ImageTexture {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> ImageTexture {
+        self.interpolation = interpolation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vector2<f32>) -> ImageTexture {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_tint(mut self, tint: Vector3<f32>) -> ImageTexture {
+        self.tint = tint;
+        self
+    }
+
+    fn texel(&self, x: i64, y: i64) -> Vector3<f32> {
+        let (w, h) = (self.image.width(), self.image.height());
+        self.image.pixels()[self.wrap.index(x, w) + self.wrap.index(y, h) * w]
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, uv: &Vector2<f32>, _point: &Vector3<f32>) -> Vector3<f32> {
+        let (w, h) = (self.image.width(), self.image.height());
+        if w == 0 || h == 0 { return self.tint; }
+        // position in pixels, measured from the top left corner since that's where the image's rows start
+        let x = uv.x * self.scale.x * w as f32;
+        let y = (1. - uv.y * self.scale.y) * h as f32;
+        let color = match self.interpolation {
+            Interpolation::Nearest => self.texel(x.floor() as i64, y.floor() as i64),
+            Interpolation::Bilinear => {
+                let (x, y) = (x - 0.5, y - 0.5); // between pixel centers
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let top = self.texel(x0, y0) * (1. - fx) + self.texel(x0 + 1, y0) * fx;
+                let bottom = self.texel(x0, y0 + 1) * (1. - fx) + self.texel(x0 + 1, y0 + 1) * fx;
+                top * (1. - fy) + bottom * fy
+            }
+        };
+        color.component_mul(&self.tint)
+    }
+}
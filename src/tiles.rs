@@ -0,0 +1,96 @@
+use std::str::FromStr;
+
+/// A rectangle of pixels rendered as one unit of work.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize
+}
+
+/// The order tiles are handed out in, and so the order they appear in while a render is in progress.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BucketOrder {
+    /// left to right, top to bottom
+    Scanline,
+    /// outwards from the center, where the subject usually is
+    Spiral,
+    /// along a Hilbert curve, keeping consecutive tiles next to each other
+    Hilbert
+}
+
+impl FromStr for BucketOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<BucketOrder, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "scanline" => Ok(BucketOrder::Scanline),
+            "spiral" => Ok(BucketOrder::Spiral),
+            "hilbert" => Ok(BucketOrder::Hilbert),
+            _ => Err(format!("unknown bucket order {} (expected scanline, spiral or hilbert)", s))
+        }
+    }
+}
+
+/// Splits a `width` x `height` image into tiles of at most `size` x `size` pixels, in the given order.
+pub fn tiles(width: usize, height: usize, size: usize, order: BucketOrder) -> Vec<Tile> {
+    let size = size.max(1);
+    let (cols, rows) = (width.div_ceil(size), height.div_ceil(size));
+    let tile = |c: usize, r: usize| Tile { x: c * size, y: r * size, width: size.min(width - c * size), height: size.min(height - r * size) };
+
+    let cells: Vec<(usize, usize)> = match order {
+        BucketOrder::Scanline => (0..rows).flat_map(|r| (0..cols).map(move |c| (c, r))).collect(),
+        BucketOrder::Spiral => spiral(cols, rows),
+        BucketOrder::Hilbert => {
+            let n = cols.max(rows).next_power_of_two();
+            let mut cells: Vec<(usize, usize)> = (0..rows).flat_map(|r| (0..cols).map(move |c| (c, r))).collect();
+            cells.sort_by_key(|&(c, r)| hilbert_index(n, c, r));
+            cells
+        }
+    };
+    cells.into_iter().map(|(c, r)| tile(c, r)).collect()
+}
+
+/// Walks a square spiral out from the middle cell, keeping the cells that fall inside the grid.
+fn spiral(cols: usize, rows: usize) -> Vec<(usize, usize)> {
+    let total = cols * rows;
+    let mut cells = Vec::with_capacity(total);
+    let (mut c, mut r) = ((cols as i64 - 1) / 2, (rows as i64 - 1) / 2);
+    let (mut dc, mut dr) = (1i64, 0i64);
+    let mut leg = 1;
+    while cells.len() < total {
+        for _ in 0..2 {
+            for _ in 0..leg {
+                if c >= 0 && r >= 0 && (c as usize) < cols && (r as usize) < rows { cells.push((c as usize, r as usize)); }
+                c += dc;
+                r += dr;
+            }
+            let turned = (-dr, dc);
+            dc = turned.0;
+            dr = turned.1;
+        }
+        leg += 1;
+    }
+    cells
+}
+
+/// Distance along the Hilbert curve filling an `n` x `n` grid (n a power of two) to the cell (x, y).
+fn hilbert_index(n: usize, mut x: usize, mut y: usize) -> usize {
+    let mut d = 0;
+    let mut s = n / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as usize;
+        let ry = (y & s > 0) as usize;
+        d += s * s * ((3 * rx) ^ ry);
+        if ry == 0 { // rotate the quadrant so the curve stays continuous
+            if rx == 1 {
+                x = n - 1 - x;
+                y = n - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    d
+}
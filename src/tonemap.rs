@@ -0,0 +1,102 @@
+use std::str::FromStr;
+use crate::Vector3;
+
+/// Maps unbounded linear radiance down to the [0, 1] range that 8-bit output can hold.
+pub trait ToneMapper {
+    fn map(&self, color: Vector3<f32>) -> Vector3<f32>;
+}
+
+/// Cuts every channel off at 1, so anything brighter clips to white.
+#[derive(Clone, Copy, Debug)]
+pub struct Clamp;
+
+impl ToneMapper for Clamp {
+    fn map(&self, color: Vector3<f32>) -> Vector3<f32> { color.map(|c| c.clamp(0., 1.)) }
+}
+
+/// Scales a pixel down by its brightest channel when that exceeds 1, keeping the hue of highlights.
+#[derive(Clone, Copy, Debug)]
+pub struct MaxChannel;
+
+impl ToneMapper for MaxChannel {
+    fn map(&self, color: Vector3<f32>) -> Vector3<f32> {
+        let max = color.x.max(color.y.max(color.z));
+        let color = if max > 1. { color / max } else { color };
+        Clamp.map(color)
+    }
+}
+
+/// `c / (1 + c)`, or the extended form that maps `white` (and anything brighter) to 1 when it is set.
+#[derive(Clone, Copy, Debug)]
+pub struct Reinhard {
+    pub white: Option<f32>
+}
+
+impl ToneMapper for Reinhard {
+    fn map(&self, color: Vector3<f32>) -> Vector3<f32> {
+        let white2 = self.white.map_or(f32::INFINITY, |w| w * w);
+        Clamp.map(color.map(|c| c * (1. + c / white2) / (1. + c)))
+    }
+}
+
+/// Krzysztof Narkowicz's fit of the ACES filmic curve.
+#[derive(Clone, Copy, Debug)]
+pub struct AcesFilmic;
+
+impl ToneMapper for AcesFilmic {
+    fn map(&self, color: Vector3<f32>) -> Vector3<f32> {
+        Clamp.map(color.map(|c| {
+            let c = c.max(0.);
+            (c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14)
+        }))
+    }
+}
+
+/// Brightens (positive `ev`) or darkens the image by `2^ev` before handing it to another tone mapper.
+#[derive(Clone, Copy, Debug)]
+pub struct Exposure<T: ToneMapper> {
+    pub ev: f32,
+    pub mapper: T
+}
+
+impl<T: ToneMapper> Exposure<T> {
+    pub fn new(ev: f32, mapper: T) -> Exposure<T> { Exposure { ev, mapper } }
+}
+
+impl<T: ToneMapper> ToneMapper for Exposure<T> {
+    fn map(&self, color: Vector3<f32>) -> Vector3<f32> { self.mapper.map(color * 2f32.powf(self.ev)) }
+}
+
+/// The built-in tone mappers, for picking one by name (e.g. from the command line).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ToneMapping {
+    Clamp,
+    MaxChannel,
+    Reinhard,
+    Aces
+}
+
+impl ToneMapper for ToneMapping {
+    fn map(&self, color: Vector3<f32>) -> Vector3<f32> {
+        match self {
+            ToneMapping::Clamp => Clamp.map(color),
+            ToneMapping::MaxChannel => MaxChannel.map(color),
+            ToneMapping::Reinhard => Reinhard { white: None }.map(color),
+            ToneMapping::Aces => AcesFilmic.map(color)
+        }
+    }
+}
+
+impl FromStr for ToneMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ToneMapping, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "clamp" => Ok(ToneMapping::Clamp),
+            "max" | "maxchannel" => Ok(ToneMapping::MaxChannel),
+            "reinhard" => Ok(ToneMapping::Reinhard),
+            "aces" => Ok(ToneMapping::Aces),
+            _ => Err(format!("unknown tone mapper {} (expected clamp, max, reinhard or aces)", s))
+        }
+    }
+}